@@ -30,6 +30,48 @@ impl AABB {
         true
     }
 
+    // the part of [t_min, t_max] the ray spends inside the box
+    pub fn hit_interval(&self, r: &Ray, mut t_min: f64, mut t_max: f64) -> Option<(f64, f64)> {
+        for a in 0..3 {
+            let inv_d = 1.0 / r.dir[a];
+            let mut t0 = (self.min[a] - r.orig[a]) * inv_d;
+            let mut t1 = (self.max[a] - r.orig[a]) * inv_d;
+            if inv_d < 0.0 {
+                swap(&mut t0, &mut t1);
+            }
+            t_min = t_min.max(t0);
+            t_max = t_max.min(t1);
+            if t_max <= t_min {
+                return None;
+            }
+        }
+        Some((t_min, t_max))
+    }
+
+    pub fn corners(&self) -> [Point; 8] {
+        let mut corners = [Point::default(); 8];
+        for (i, c) in corners.iter_mut().enumerate() {
+            *c = Point::new(
+                if i & 1 == 0 { self.min.x } else { self.max.x },
+                if i & 2 == 0 { self.min.y } else { self.max.y },
+                if i & 4 == 0 { self.min.z } else { self.max.z },
+            );
+        }
+        corners
+    }
+
+    // box around all eight transformed corners, so it stays conservative under rotation
+    pub fn transform(&self, mat: &glam::DMat4) -> AABB {
+        let corners = self
+            .corners()
+            .map(|c| Point::from(mat.transform_point3(c.into())));
+        corners[1..]
+            .iter()
+            .fold(AABB::new(corners[0], corners[0]), |bx, c| {
+                AABB::from_surrounding(bx, AABB::new(*c, *c))
+            })
+    }
+
     pub fn from_surrounding(box0: AABB, box1: AABB) -> AABB {
         let small = Point::new(
             box0.min.x.min(box1.min.x),
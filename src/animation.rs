@@ -0,0 +1,225 @@
+use glam::{DMat4, DVec3};
+
+use crate::{scenes::SceneConfig, vec3::Vec3};
+
+#[derive(Clone, Copy)]
+pub enum Interpolation {
+    Linear,
+    // catmull-rom through the keys
+    Spline,
+}
+
+pub trait Interpolate: Copy {
+    fn lerp(a: Self, b: Self, t: f64) -> Self;
+
+    // catmull-rom segment between p1 and p2. `scale` stretches the tangents at p1 and p2
+    // for keys that aren't evenly spaced, (1, 1) when they are
+    fn spline(p0: Self, p1: Self, p2: Self, p3: Self, t: f64, scale: (f64, f64)) -> Self;
+}
+
+impl Interpolate for f64 {
+    fn lerp(a: Self, b: Self, t: f64) -> Self {
+        a + (b - a) * t
+    }
+
+    fn spline(p0: Self, p1: Self, p2: Self, p3: Self, t: f64, scale: (f64, f64)) -> Self {
+        let t2 = t * t;
+        let t3 = t2 * t;
+        let m1 = 0.5 * (p2 - p0) * scale.0;
+        let m2 = 0.5 * (p3 - p1) * scale.1;
+        (2.0 * t3 - 3.0 * t2 + 1.0) * p1
+            + (t3 - 2.0 * t2 + t) * m1
+            + (3.0 * t2 - 2.0 * t3) * p2
+            + (t3 - t2) * m2
+    }
+}
+
+impl Interpolate for Vec3 {
+    fn lerp(a: Self, b: Self, t: f64) -> Self {
+        a + (b - a) * t
+    }
+
+    fn spline(p0: Self, p1: Self, p2: Self, p3: Self, t: f64, scale: (f64, f64)) -> Self {
+        Vec3::new(
+            f64::spline(p0.x, p1.x, p2.x, p3.x, t, scale),
+            f64::spline(p0.y, p1.y, p2.y, p3.y, t, scale),
+            f64::spline(p0.z, p1.z, p2.z, p3.z, t, scale),
+        )
+    }
+}
+
+// matrices are decomposed into scale, rotation and translation. rotation is always slerped
+// between the neighbouring keys
+impl Interpolate for DMat4 {
+    fn lerp(a: Self, b: Self, t: f64) -> Self {
+        let (sa, ra, ta) = a.to_scale_rotation_translation();
+        let (sb, rb, tb) = b.to_scale_rotation_translation();
+        DMat4::from_scale_rotation_translation(sa.lerp(sb, t), ra.slerp(rb, t), ta.lerp(tb, t))
+    }
+
+    fn spline(p0: Self, p1: Self, p2: Self, p3: Self, t: f64, scale: (f64, f64)) -> Self {
+        let (s0, _, tr0) = p0.to_scale_rotation_translation();
+        let (s1, r1, tr1) = p1.to_scale_rotation_translation();
+        let (s2, r2, tr2) = p2.to_scale_rotation_translation();
+        let (s3, _, tr3) = p3.to_scale_rotation_translation();
+
+        let spline3 = |a: DVec3, b: DVec3, c: DVec3, d: DVec3| -> DVec3 {
+            Vec3::spline(a.into(), b.into(), c.into(), d.into(), t, scale).into()
+        };
+
+        DMat4::from_scale_rotation_translation(
+            spline3(s0, s1, s2, s3),
+            r1.slerp(r2, t),
+            spline3(tr0, tr1, tr2, tr3),
+        )
+    }
+}
+
+// values keyed by time in seconds. sampling before the first or after the last key holds
+// that key's value
+#[derive(Clone)]
+pub struct Track<T: Interpolate> {
+    keys: Vec<(f64, T)>,
+    interpolation: Interpolation,
+}
+
+impl<T: Interpolate> Track<T> {
+    pub fn new(interpolation: Interpolation) -> Self {
+        Self {
+            keys: vec![],
+            interpolation,
+        }
+    }
+
+    pub fn with_key(mut self, time: f64, value: T) -> Self {
+        let idx = self.keys.partition_point(|(t, _)| *t <= time);
+        self.keys.insert(idx, (time, value));
+        self
+    }
+
+    pub fn sample(&self, time: f64) -> T {
+        let first = self.keys.first().expect("track has no keys");
+        let last = self.keys.last().unwrap();
+        if time <= first.0 {
+            return first.1;
+        }
+        if time >= last.0 {
+            return last.1;
+        }
+
+        // keys[i] <= time < keys[i + 1]
+        let i = self.keys.partition_point(|(t, _)| *t <= time) - 1;
+        let (t1, p1) = self.keys[i];
+        let (t2, p2) = self.keys[i + 1];
+        let t = (time - t1) / (t2 - t1);
+
+        match self.interpolation {
+            Interpolation::Linear => T::lerp(p1, p2, t),
+            Interpolation::Spline => {
+                // the ends act as if there were another key the same distance away again
+                let (t0, p0) = if i > 0 {
+                    self.keys[i - 1]
+                } else {
+                    (t1 - (t2 - t1), p1)
+                };
+                let (t3, p3) = if i + 2 < self.keys.len() {
+                    self.keys[i + 2]
+                } else {
+                    (t2 + (t2 - t1), p2)
+                };
+                // tangents are per second across the neighbouring keys, not per segment
+                let scale = (2.0 * (t2 - t1) / (t2 - t0), 2.0 * (t2 - t1) / (t3 - t1));
+                T::spline(p0, p1, p2, p3, t, scale)
+            }
+        }
+    }
+}
+
+pub struct CameraTrack {
+    pub lookfrom: Track<Vec3>,
+    pub lookat: Track<Vec3>,
+    pub vfov: Track<f64>,
+}
+
+impl CameraTrack {
+    pub fn apply(&self, cfg: &mut SceneConfig, time: f64) {
+        cfg.lookfrom = self.lookfrom.sample(time);
+        cfg.lookat = self.lookat.sample(time);
+        cfg.vfov = self.vfov.sample(time);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{DMat4, DQuat, DVec3};
+
+    use super::{Interpolate, Interpolation, Track};
+
+    #[test]
+    fn holds_the_ends_and_passes_through_keys() {
+        for interpolation in [Interpolation::Linear, Interpolation::Spline] {
+            let track = Track::new(interpolation)
+                .with_key(3.0, 5.0)
+                .with_key(1.0, 2.0)
+                .with_key(2.0, -1.0);
+            assert_eq!(track.sample(0.0), 2.0);
+            assert_eq!(track.sample(1.0), 2.0);
+            assert_eq!(track.sample(2.0), -1.0);
+            assert_eq!(track.sample(3.0), 5.0);
+            assert_eq!(track.sample(10.0), 5.0);
+        }
+
+        let linear = Track::new(Interpolation::Linear)
+            .with_key(1.0, 2.0)
+            .with_key(2.0, -1.0);
+        assert!((linear.sample(1.25) - 1.25).abs() < 1e-12);
+    }
+
+    #[test]
+    fn spline_speed_is_smooth_across_uneven_keys() {
+        // a steady 3 units a second, keyed unevenly, stays a straight line away from the
+        // end keys, which ease in and out
+        let track = [0.0, 0.5, 2.0, 2.25, 4.0]
+            .iter()
+            .fold(Track::new(Interpolation::Spline), |track, &t| {
+                track.with_key(t, 3.0 * t)
+            });
+        for time in [0.6, 1.3, 2.1, 2.2] {
+            assert!((track.sample(time) - 3.0 * time).abs() < 1e-9);
+        }
+
+        let track = Track::new(Interpolation::Spline)
+            .with_key(0.0, 0.0)
+            .with_key(1.0, 1.0)
+            .with_key(4.0, -2.0)
+            .with_key(4.5, 0.0);
+        let e = 1e-6;
+        for key in [1.0, 4.0] {
+            let before = (track.sample(key) - track.sample(key - e)) / e;
+            let after = (track.sample(key + e) - track.sample(key)) / e;
+            assert!((before - after).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn matrices_interpolate_their_parts() {
+        let a = DMat4::from_scale_rotation_translation(DVec3::ONE, DQuat::IDENTITY, DVec3::ZERO);
+        let b = DMat4::from_scale_rotation_translation(
+            DVec3::splat(3.0),
+            DQuat::from_rotation_y(std::f64::consts::FRAC_PI_2),
+            DVec3::new(2.0, 4.0, 6.0),
+        );
+        let (s, r, t) = DMat4::lerp(a, b, 0.5).to_scale_rotation_translation();
+        assert!((s - DVec3::splat(2.0)).length() < 1e-9);
+        assert!(r.angle_between(DQuat::from_rotation_y(std::f64::consts::FRAC_PI_4)) < 1e-9);
+        assert!((t - DVec3::new(1.0, 2.0, 3.0)).length() < 1e-9);
+
+        let track = Track::new(Interpolation::Spline)
+            .with_key(0.0, a)
+            .with_key(1.0, b);
+        assert!(track.sample(0.0).abs_diff_eq(a, 1e-12));
+        assert!(track.sample(1.0).abs_diff_eq(b, 1e-12));
+        let (_, r, _) = track.sample(0.5).to_scale_rotation_translation();
+        assert!(r.angle_between(DQuat::from_rotation_y(std::f64::consts::FRAC_PI_4)) < 1e-9);
+    }
+}
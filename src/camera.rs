@@ -1,9 +1,77 @@
 use crate::{
+    hittable::Hittable,
+    lens::RealisticCamera,
+    object::Object,
     ray::Ray,
-    utils::random_double,
+    stereo::StereoCamera,
+    utils::random_double_normal,
     vec3::{Point, Vec3},
 };
 
+// how far open the shutter is over the exposure, which weights the ray times
+#[derive(Clone, Copy, Default)]
+pub enum ShutterCurve {
+    // fully open for the whole interval
+    #[default]
+    Box,
+    // opens over the first fraction of the interval and closes over the last
+    Trapezoid(Trapezoid),
+}
+
+// made with ShutterCurve::trapezoid, which keeps the two fractions inside the interval
+#[derive(Clone, Copy)]
+pub struct Trapezoid {
+    open: f64,
+    close: f64,
+}
+
+impl ShutterCurve {
+    // fractions that add up to more than the interval are shrunk to meet in the middle
+    pub fn trapezoid(open: f64, close: f64) -> Self {
+        let (open, close) = (open.max(0.0), close.max(0.0));
+        let fit = (open + close).max(1.0);
+        ShutterCurve::Trapezoid(Trapezoid {
+            open: open / fit,
+            close: close / fit,
+        })
+    }
+
+    // maps a uniform sample to a time in [0, 1] distributed by the curve
+    pub fn sample(&self, u: f64) -> f64 {
+        match *self {
+            ShutterCurve::Box => u,
+            ShutterCurve::Trapezoid(Trapezoid { open, close }) => {
+                let flat = 1.0 - open - close;
+                let area = 0.5 * open + flat + 0.5 * close;
+                let c = u * area;
+                if c < 0.5 * open {
+                    (2.0 * open * c).sqrt()
+                } else if c < 0.5 * open + flat {
+                    open + (c - 0.5 * open)
+                } else {
+                    1.0 - (2.0 * close * (area - c)).max(0.0).sqrt()
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct Shutter {
+    pub curve: ShutterCurve,
+    // delay between the top and bottom scanlines starting their exposure, 0 for a global shutter
+    pub rolling: f64,
+}
+
+impl Shutter {
+    // a ray time inside the exposure `time` for the scanline at height t
+    pub fn sample_time(&self, time: (f64, f64), t: f64) -> f64 {
+        // the top scanline is read out first
+        let offset = self.rolling * (1.0 - t);
+        time.0 + offset + self.curve.sample(random_double_normal()) * (time.1 - time.0)
+    }
+}
+
 #[derive(Clone, Copy)]
 pub struct Camera {
     origin: Point,
@@ -12,8 +80,11 @@ pub struct Camera {
     vertical: Vec3,
     u: Vec3,
     v: Vec3,
+    w: Vec3,
     lens_radius: f64,
+    focus_dist: f64,
     time: (f64, f64),
+    shutter: Shutter,
 }
 
 impl Camera {
@@ -49,8 +120,11 @@ impl Camera {
             vertical,
             u,
             v,
+            w,
             lens_radius,
+            focus_dist,
             time: (0.0, 0.0),
+            shutter: Shutter::default(),
         }
     }
 }
@@ -65,7 +139,7 @@ impl Camera {
             dir: self.lower_left_corner + (s * self.horizontal) + (t * self.vertical)
                 - self.origin
                 - offset,
-            time: random_double(self.time.0, self.time.1),
+            time: self.shutter.sample_time(self.time, t),
         }
     }
 
@@ -73,4 +147,141 @@ impl Camera {
         self.time = (t0, t1);
         *self
     }
+
+    pub fn with_shutter(&mut self, shutter: Shutter) -> Self {
+        self.shutter = shutter;
+        *self
+    }
+
+    pub fn with_focus_dist(&mut self, focus_dist: f64) -> Self {
+        let scale = focus_dist / self.focus_dist;
+        self.horizontal *= scale;
+        self.vertical *= scale;
+        self.lower_left_corner =
+            self.origin - self.horizontal / 2.0 - self.vertical / 2.0 - focus_dist * self.w;
+        self.focus_dist = focus_dist;
+        *self
+    }
+
+    // distance along the view axis to whatever the pinhole ray through (s, t) hits first
+    pub fn focus_distance_at(&self, world: &Object, s: f64, t: f64) -> Option<f64> {
+        let dir =
+            self.lower_left_corner + (s * self.horizontal) + (t * self.vertical) - self.origin;
+        let r = Ray::new(self.origin, dir).with_time(self.time.0);
+
+        world
+            .hit(&r, 0.001, f64::MAX)
+            .map(|rec| (rec.p - self.origin).dot(-self.w))
+            .filter(|d| *d > 0.0)
+    }
+
+    // moves the eye `offset` along u, keeping the image plane fixed at the convergence distance
+    // so objects there land on the same pixel in both eyes
+    pub fn with_eye_offset(&mut self, offset: f64, convergence: f64) -> Self {
+        let shift = offset * (1.0 - self.focus_dist / convergence);
+        self.origin += self.u * offset;
+        self.lower_left_corner += self.u * shift;
+        *self
+    }
+
+    // omnidirectional stereo: longitude theta and latitude phi in radians around the view axis.
+    // the eye sits `offset` out on a circle of radius |offset| tangent to the ray, toed in to meet
+    // the other eye at the convergence distance
+    pub fn get_omni_ray(&self, theta: f64, phi: f64, offset: f64, convergence: f64, t: f64) -> Ray {
+        let forward = -self.w;
+        let dir = phi.cos() * (theta.sin() * self.u + theta.cos() * forward) + phi.sin() * self.v;
+        let eye = offset * (theta.cos() * self.u - theta.sin() * forward);
+
+        let dir = if convergence.is_finite() {
+            convergence * dir - eye
+        } else {
+            dir
+        };
+
+        Ray {
+            orig: self.origin + eye,
+            dir,
+            time: self.shutter.sample_time(self.time, t),
+        }
+    }
+}
+
+pub enum CameraType {
+    Thin(Camera),
+    Realistic(RealisticCamera),
+    Stereo(Box<StereoCamera>),
+}
+
+impl CameraType {
+    // the ray through (s, t) and the weight its radiance should be scaled by.
+    // None means the ray never made it out of the lens
+    pub fn generate_ray(&self, s: f64, t: f64) -> Option<(Ray, f64)> {
+        match self {
+            CameraType::Thin(c) => Some((c.get_ray(s, t), 1.0)),
+            CameraType::Realistic(c) => c.generate_ray(s, t),
+            CameraType::Stereo(c) => Some((c.get_ray(s, t), 1.0)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Shutter, ShutterCurve, Trapezoid};
+
+    // fraction of the exposure let through by time x
+    fn cdf(curve: ShutterCurve, x: f64) -> f64 {
+        match curve {
+            ShutterCurve::Box => x,
+            ShutterCurve::Trapezoid(Trapezoid { open, close }) => {
+                let area = 1.0 - 0.5 * (open + close);
+                let c = if x < open {
+                    x * x / (2.0 * open)
+                } else if x < 1.0 - close {
+                    0.5 * open + (x - open)
+                } else {
+                    area - (1.0 - x) * (1.0 - x) / (2.0 * close)
+                };
+                c / area
+            }
+        }
+    }
+
+    #[test]
+    fn trapezoid_sampling_follows_its_cdf() {
+        match ShutterCurve::trapezoid(0.8, 0.6) {
+            ShutterCurve::Trapezoid(Trapezoid { open, close }) => {
+                assert!((open + close - 1.0).abs() < 1e-12);
+                assert!((open / close - 0.8 / 0.6).abs() < 1e-12);
+            }
+            ShutterCurve::Box => unreachable!(),
+        }
+
+        for curve in [
+            ShutterCurve::Box,
+            ShutterCurve::trapezoid(0.3, 0.2),
+            ShutterCurve::trapezoid(0.8, 0.6),
+        ] {
+            for i in 0..=100 {
+                let u = i as f64 / 100.0;
+                let x = curve.sample(u);
+                assert!((0.0..=1.0).contains(&x));
+                assert!((cdf(curve, x) - u).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn sample_time_stays_in_the_exposure() {
+        let shutter = Shutter {
+            curve: ShutterCurve::trapezoid(0.5, 0.5),
+            rolling: 0.2,
+        };
+        for t in [0.0, 0.5, 1.0] {
+            let offset = 0.2 * (1.0 - t);
+            for _ in 0..200 {
+                let time = shutter.sample_time((1.0, 3.0), t);
+                assert!(time >= 1.0 + offset && time <= 3.0 + offset);
+            }
+        }
+    }
 }
@@ -0,0 +1,352 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::AABB,
+    hittable::{object_space_ray, world_space_record, HitRecord, Hittable, MatTransform},
+    object::Object,
+    ray::Ray,
+    rect::RectBox,
+    sphere::Sphere,
+    vec3::Point,
+};
+
+// a stretch of the ray inside a closed shape. the records keep the outward normal of the shape
+// rather than one flipped to face the ray
+#[derive(Clone)]
+pub struct Interval {
+    pub enter: HitRecord,
+    pub exit: HitRecord,
+}
+
+pub trait Solid {
+    // every stretch of the whole line through the ray that is inside the shape, in order. this
+    // includes anything behind the ray origin, as the origin may already be inside
+    fn intervals(&self, r: &Ray) -> Vec<Interval>;
+}
+
+impl Solid for Sphere {
+    fn intervals(&self, r: &Ray) -> Vec<Interval> {
+        let oc = r.orig - self.center;
+        let a = r.dir.length_squared();
+        let half_b = oc.dot(r.dir);
+        let c = oc.length_squared() - self.radius * self.radius;
+
+        let disc = half_b * half_b - a * c;
+        if disc < 0.0 {
+            return vec![];
+        }
+        let sqrtd = disc.sqrt();
+
+        let boundary = |t: f64| {
+            let p = r.at(t);
+            let normal = (p - self.center) / self.radius;
+            let (dpdu, dpdv) = self.get_sphere_dpduv(&normal);
+            HitRecord {
+                p,
+                t,
+                normal,
+                uv: self.get_sphere_uv(&normal),
+                mat: self.mat.clone(),
+                dpdu,
+                dpdv,
+                ..Default::default()
+            }
+        };
+        vec![Interval {
+            enter: boundary((-half_b - sqrtd) / a),
+            exit: boundary((-half_b + sqrtd) / a),
+        }]
+    }
+}
+
+// the box is convex, so the ray is inside between the first and last side it crosses
+impl Solid for RectBox {
+    fn intervals(&self, r: &Ray) -> Vec<Interval> {
+        // not every side's own normal faces out, but they all face away from the centre
+        let bx = self.bounding_box((0.0, 0.0)).unwrap();
+        let centre = 0.5 * (bx.min + bx.max);
+        let mut crossings: Vec<HitRecord> = self
+            .sides
+            .objects
+            .iter()
+            .filter_map(|side| side.hit(r, f64::NEG_INFINITY, f64::INFINITY))
+            .map(|mut rec| {
+                if rec.normal.dot(rec.p - centre) < 0.0 {
+                    rec.normal = -rec.normal;
+                }
+                rec
+            })
+            .collect();
+        if crossings.len() < 2 {
+            return vec![];
+        }
+
+        crossings.sort_by(|x, y| x.t.total_cmp(&y.t));
+        vec![Interval {
+            enter: crossings.first().unwrap().clone(),
+            exit: crossings.last().unwrap().clone(),
+        }]
+    }
+}
+
+impl Solid for MatTransform {
+    fn intervals(&self, r: &Ray) -> Vec<Interval> {
+        self.ptr
+            .intervals(&object_space_ray(&self.mat_i, r))
+            .into_iter()
+            .map(|iv| Interval {
+                enter: world_space_record(&self.mat, &self.mat_i, iv.enter),
+                exit: world_space_record(&self.mat, &self.mat_i, iv.exit),
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum CsgOp {
+    Union,
+    Intersection,
+    // the first shape with the second carved out of it
+    Difference,
+}
+
+impl CsgOp {
+    fn inside(&self, in_a: bool, in_b: bool) -> bool {
+        match self {
+            CsgOp::Union => in_a || in_b,
+            CsgOp::Intersection => in_a && in_b,
+            CsgOp::Difference => in_a && !in_b,
+        }
+    }
+}
+
+// boolean combination of two closed shapes: spheres, boxes, transforms of them or other Csg
+// nodes. every surface keeps its own material, except where a difference cuts into the first
+// shape, which shows the first shape's material
+pub struct Csg {
+    op: CsgOp,
+    a: Arc<Object>,
+    b: Arc<Object>,
+    aabb: Option<AABB>,
+}
+
+impl Csg {
+    pub fn new(op: CsgOp, a: Arc<Object>, b: Arc<Object>) -> Self {
+        let (box_a, box_b) = (a.bounding_box((0.0, 0.0)), b.bounding_box((0.0, 0.0)));
+        let aabb = match op {
+            CsgOp::Union => box_a.zip(box_b).map(|(x, y)| AABB::from_surrounding(x, y)),
+            CsgOp::Intersection => box_a.zip(box_b).map(|(x, y)| {
+                AABB::new(
+                    Point::new(
+                        x.min.x.max(y.min.x),
+                        x.min.y.max(y.min.y),
+                        x.min.z.max(y.min.z),
+                    ),
+                    Point::new(
+                        x.max.x.min(y.max.x),
+                        x.max.y.min(y.max.y),
+                        x.max.z.min(y.max.z),
+                    ),
+                )
+            }),
+            CsgOp::Difference => box_a,
+        };
+
+        Self { op, a, b, aabb }
+    }
+}
+
+impl Solid for Csg {
+    fn intervals(&self, r: &Ray) -> Vec<Interval> {
+        // (t, entering, from a) for every boundary of both shapes
+        let mut events = vec![];
+        for (from_a, shape) in [(true, &self.a), (false, &self.b)] {
+            for iv in shape.intervals(r) {
+                events.push((true, from_a, iv.enter));
+                events.push((false, from_a, iv.exit));
+            }
+        }
+        events.sort_by(|x, y| x.2.t.total_cmp(&y.2.t));
+
+        let (mut in_a, mut in_b) = (false, false);
+        let mut a_mat = None;
+        let mut enter = None;
+        let mut result = vec![];
+
+        for (entering, from_a, mut rec) in events {
+            let was_inside = self.op.inside(in_a, in_b);
+            if from_a {
+                in_a = entering;
+                if entering {
+                    a_mat = Some(rec.mat.clone());
+                }
+            } else {
+                in_b = entering;
+            }
+
+            let inside = self.op.inside(in_a, in_b);
+            if inside == was_inside {
+                continue;
+            }
+
+            // leaving the second shape is entering a difference, so its normal turns inwards
+            if inside != entering {
+                rec.normal = -rec.normal;
+            }
+            if let (CsgOp::Difference, false, Some(mat)) = (self.op, from_a, &a_mat) {
+                rec.mat = mat.clone();
+            }
+
+            if inside {
+                enter = Some(rec);
+            } else if let Some(enter) = enter.take() {
+                result.push(Interval { enter, exit: rec });
+            }
+        }
+        result
+    }
+}
+
+impl Hittable for Csg {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        if let Some(aabb) = self.aabb {
+            if !aabb.hit(r, t_min, t_max) {
+                return None;
+            }
+        }
+
+        self.intervals(r)
+            .into_iter()
+            .flat_map(|iv| [iv.enter, iv.exit])
+            .find(|rec| rec.t >= t_min && rec.t <= t_max)
+            .map(|mut rec| {
+                let outward_normal = rec.normal;
+                rec.set_face_normal(r, outward_normal);
+                rec
+            })
+    }
+
+    fn bounding_box(&self, _time: (f64, f64)) -> Option<AABB> {
+        self.aabb
+    }
+}
+
+// shapes that aren't closed have no inside, so they never contribute to a Csg
+impl Solid for Object {
+    fn intervals(&self, r: &Ray) -> Vec<Interval> {
+        match self {
+            Object::Sphere(s) => s.intervals(r),
+            Object::RectBox(b) => b.intervals(r),
+            Object::MatTransform(mt) => mt.intervals(r),
+            Object::Csg(c) => c.intervals(r),
+            _ => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{Csg, CsgOp, Solid};
+    use crate::{
+        hittable::Hittable,
+        material::{Lambertain, Material},
+        object::Object,
+        ray::Ray,
+        rect::RectBox,
+        sphere::Sphere,
+        vec3::{Point, Vec3},
+    };
+
+    fn lambertian() -> Arc<Material> {
+        Arc::new(Material::Lambertain(Lambertain::default()))
+    }
+
+    #[test]
+    fn box_intervals_have_outward_normals() {
+        let bx = RectBox::new(
+            &Point::new(-1.0, 0.0, 2.0),
+            &Point::new(3.0, 1.0, 5.0),
+            lambertian(),
+        );
+        let centre = Point::new(1.0, 0.5, 3.5);
+        for axis in [
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+        ] {
+            for dir in [axis, -axis] {
+                let intervals = bx.intervals(&Ray::new(centre - 10.0 * dir, dir));
+                assert_eq!(intervals.len(), 1);
+                let iv = &intervals[0];
+                assert!(iv.enter.t < iv.exit.t);
+                assert!((iv.enter.normal + dir).length() < 1e-9);
+                assert!((iv.exit.normal - dir).length() < 1e-9);
+            }
+        }
+        let past = Ray::new(Point::new(-5.0, 2.0, 3.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(bx.intervals(&past).is_empty());
+    }
+
+    // a unit sphere at the origin and a box from x = 0.5 to 2.5, crossed along the x axis
+    fn pair(op: CsgOp) -> (Csg, Arc<Material>) {
+        let sphere_mat = lambertian();
+        let sphere = Arc::new(Object::Sphere(Sphere::new(
+            Point::default(),
+            1.0,
+            sphere_mat.clone(),
+        )));
+        let bx = Arc::new(Object::RectBox(RectBox::new(
+            &Point::new(0.5, -1.0, -1.0),
+            &Point::new(2.5, 1.0, 1.0),
+            lambertian(),
+        )));
+        (Csg::new(op, sphere, bx), sphere_mat)
+    }
+
+    #[test]
+    fn csg_ops_pick_the_right_stretches() {
+        let r = Ray::new(Point::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let x = Vec3::new(1.0, 0.0, 0.0);
+        let cases = [
+            (CsgOp::Union, (-1.0, 2.5)),
+            (CsgOp::Intersection, (0.5, 1.0)),
+            (CsgOp::Difference, (-1.0, 0.5)),
+        ];
+        for (op, (enter, exit)) in cases {
+            let (csg, _) = pair(op);
+            let intervals = csg.intervals(&r);
+            assert_eq!(intervals.len(), 1);
+            let iv = &intervals[0];
+            assert!((iv.enter.p.x - enter).abs() < 1e-9);
+            assert!((iv.exit.p.x - exit).abs() < 1e-9);
+            assert!((iv.enter.normal + x).length() < 1e-9);
+            assert!((iv.exit.normal - x).length() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn difference_faces_out_of_the_cut() {
+        let (csg, sphere_mat) = pair(CsgOp::Difference);
+
+        let from_outside = Ray::new(Point::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let rec = csg.hit(&from_outside, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.p.x + 1.0).abs() < 1e-9 && rec.front_face);
+
+        // the cut wall shows the sphere's material, and faces the +x side it was cut from
+        let from_inside = Ray::new(Point::default(), Vec3::new(1.0, 0.0, 0.0));
+        let rec = csg.hit(&from_inside, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.p.x - 0.5).abs() < 1e-9 && !rec.front_face);
+        assert!((rec.normal + Vec3::new(1.0, 0.0, 0.0)).length() < 1e-9);
+        assert!(Arc::ptr_eq(&rec.mat, &sphere_mat));
+
+        let into_cut = Ray::new(Point::new(2.0, 0.0, 0.0), Vec3::new(-1.0, 0.0, 0.0));
+        let rec = csg.hit(&into_cut, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.p.x - 0.5).abs() < 1e-9 && rec.front_face);
+        assert!((rec.normal - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-9);
+
+        let through_box_only = Ray::new(Point::new(2.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        assert!(csg.hit(&through_box_only, 0.001, f64::INFINITY).is_none());
+    }
+}
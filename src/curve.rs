@@ -0,0 +1,248 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::AABB,
+    hittable::{HitRecord, Hittable},
+    material::Material,
+    onb::ONB,
+    ray::Ray,
+    vec3::{Point, Vec3},
+};
+
+#[derive(Clone, Copy, Default)]
+pub enum CurveMode {
+    // a flat strip that always faces the ray
+    #[default]
+    Flat,
+    // still flat, but shaded as if it were a round tube
+    Cylinder,
+}
+
+// cubic bezier curve with a width that changes linearly from one end to the other. meant for
+// thin things like hair, fur and grass, where lots of them go in a BVHNode. uv is the position
+// along the curve and across it
+pub struct Curve {
+    cp: [Point; 4],
+    width: (f64, f64),
+    mode: CurveMode,
+    pub mat: Arc<Material>,
+}
+
+fn lerp(t: f64, a: f64, b: f64) -> f64 {
+    (1.0 - t) * a + t * b
+}
+
+// point and tangent at t
+fn eval_bezier(cp: &[Point; 4], t: f64) -> (Point, Vec3) {
+    let mix = |a: Point, b: Point| (1.0 - t) * a + t * b;
+    let l1 = [mix(cp[0], cp[1]), mix(cp[1], cp[2]), mix(cp[2], cp[3])];
+    let l2 = [mix(l1[0], l1[1]), mix(l1[1], l1[2])];
+    (mix(l2[0], l2[1]), 3.0 * (l2[1] - l2[0]))
+}
+
+fn split_bezier(cp: &[Point; 4]) -> ([Point; 4], [Point; 4]) {
+    let mid = |a: Point, b: Point| 0.5 * (a + b);
+    let l1 = [mid(cp[0], cp[1]), mid(cp[1], cp[2]), mid(cp[2], cp[3])];
+    let l2 = [mid(l1[0], l1[1]), mid(l1[1], l1[2])];
+    let m = mid(l2[0], l2[1]);
+    ([cp[0], l1[0], l2[0], m], [m, l2[1], l1[2], cp[3]])
+}
+
+impl Curve {
+    pub fn new(cp: [Point; 4], width: (f64, f64), mat: Arc<Material>) -> Self {
+        Self {
+            cp,
+            width,
+            mode: CurveMode::default(),
+            mat,
+        }
+    }
+
+    pub fn with_mode(mut self, mode: CurveMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    // splits the curve, in a space where the ray starts at the origin and runs down +z, until
+    // the pieces are close enough to straight to test as lines. returns (distance along the
+    // ray, u, v) of the closest hit
+    fn recursive_hit(
+        &self,
+        cp: &[Point; 4],
+        u: (f64, f64),
+        z_range: (f64, f64),
+        depth: u32,
+    ) -> Option<(f64, f64, f64)> {
+        let half_width =
+            0.5 * lerp(u.0, self.width.0, self.width.1).max(lerp(u.1, self.width.0, self.width.1));
+        let (mut min, mut max) = (cp[0], cp[0]);
+        for p in &cp[1..] {
+            min = Point::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z));
+            max = Point::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z));
+        }
+        if min.x - half_width > 0.0
+            || max.x + half_width < 0.0
+            || min.y - half_width > 0.0
+            || max.y + half_width < 0.0
+            || min.z - half_width > z_range.1
+            || max.z + half_width < z_range.0
+        {
+            return None;
+        }
+
+        if depth > 0 {
+            let (a, b) = split_bezier(cp);
+            let mid = 0.5 * (u.0 + u.1);
+            let first = self.recursive_hit(&a, (u.0, mid), z_range, depth - 1);
+            let z_range = (z_range.0, first.map_or(z_range.1, |h| h.0));
+            return self
+                .recursive_hit(&b, (mid, u.1), z_range, depth - 1)
+                .or(first);
+        }
+
+        // the ray has to pass between the lines square to the curve at each end, so
+        // neighbouring pieces don't both claim it
+        let start = (cp[1].y - cp[0].y) * -cp[0].y + cp[0].x * (cp[0].x - cp[1].x);
+        let end = (cp[2].y - cp[3].y) * -cp[3].y + cp[3].x * (cp[3].x - cp[2].x);
+        if start < 0.0 || end < 0.0 {
+            return None;
+        }
+
+        // closest point of the piece to the ray, treating it as a line
+        let (dx, dy) = (cp[3].x - cp[0].x, cp[3].y - cp[0].y);
+        let denom = dx * dx + dy * dy;
+        if denom == 0.0 {
+            return None;
+        }
+        let w = ((-cp[0].x * dx - cp[0].y * dy) / denom).clamp(0.0, 1.0);
+        let hit_u = lerp(w, u.0, u.1);
+        let hit_width = lerp(hit_u, self.width.0, self.width.1);
+
+        let (pc, dpcdw) = eval_bezier(cp, w);
+        let dist2 = pc.x * pc.x + pc.y * pc.y;
+        if dist2 > 0.25 * hit_width * hit_width || pc.z < z_range.0 || pc.z > z_range.1 {
+            return None;
+        }
+
+        let dist = dist2.sqrt();
+        let side = dpcdw.x * -pc.y + pc.x * dpcdw.y;
+        let v = if side > 0.0 {
+            0.5 + dist / hit_width
+        } else {
+            0.5 - dist / hit_width
+        };
+        Some((pc.z, hit_u, v))
+    }
+}
+
+impl Hittable for Curve {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let len = r.dir.length();
+        let frame = ONB::from_w(&r.dir);
+        let to_ray = |p: Point| {
+            let d = p - r.orig;
+            Point::new(d.dot(frame.u), d.dot(frame.v), d.dot(frame.w))
+        };
+        let cp = self.cp.map(to_ray);
+
+        // enough splits that each piece is within a twentieth of the width of a straight line
+        let mut bend: f64 = 0.0;
+        for i in 0..2 {
+            let d = cp[i] - 2.0 * cp[i + 1] + cp[i + 2];
+            bend = bend.max(d.x.abs()).max(d.y.abs()).max(d.z.abs());
+        }
+        let eps = 0.05 * self.width.0.max(self.width.1);
+        let depth = ((2.0_f64.sqrt() * 6.0 * bend / (8.0 * eps)).log2() / 2.0).clamp(0.0, 10.0);
+
+        let (z, u, v) =
+            self.recursive_hit(&cp, (0.0, 1.0), (t_min * len, t_max * len), depth as u32)?;
+        let t = z / len;
+        let p = r.at(t);
+
+        // normal facing back along the ray, square to the curve
+        let (centre, tangent) = eval_bezier(&self.cp, u);
+        let tangent = tangent.unit();
+        let back = -r.dir / len;
+        let flat = (back - back.dot(tangent) * tangent).unit();
+        let outward_normal = match self.mode {
+            CurveMode::Flat => flat,
+            CurveMode::Cylinder => {
+                // lean towards the edge the ray passed by, as far as a tube of that width would
+                let offset = p - centre;
+                let across = offset - offset.dot(tangent) * tangent;
+                let radius = 0.5 * lerp(u, self.width.0, self.width.1);
+                let k = (across.length() / radius).min(1.0);
+                if across.length_squared() > 0.0 {
+                    (1.0 - k * k).sqrt() * flat + k * across.unit()
+                } else {
+                    flat
+                }
+            }
+        };
+
+        let mut rec = HitRecord {
+            t,
+            p,
+            uv: (u, v),
+            mat: self.mat.clone(),
+            ..Default::default()
+        };
+        rec.set_face_normal(r, outward_normal);
+        Some(rec)
+    }
+
+    fn bounding_box(&self, _time: (f64, f64)) -> Option<AABB> {
+        let half_width = 0.5 * self.width.0.max(self.width.1);
+        let (mut min, mut max) = (self.cp[0], self.cp[0]);
+        for p in &self.cp[1..] {
+            min = Point::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z));
+            max = Point::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z));
+        }
+        let pad = Vec3::new(half_width, half_width, half_width);
+        Some(AABB::new(min - pad, max + pad))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{Curve, CurveMode};
+    use crate::{
+        hittable::Hittable,
+        material::{Lambertain, Material},
+        ray::Ray,
+        vec3::{Point, Vec3},
+    };
+
+    #[test]
+    fn tapered_curve() {
+        // arch from x = 0 to x = 3 in the xy plane, narrowing from 0.4 to 0.1
+        let curve = Curve::new(
+            [
+                Point::new(0.0, 0.0, 0.0),
+                Point::new(1.0, 1.0, 0.0),
+                Point::new(2.0, 1.0, 0.0),
+                Point::new(3.0, 0.0, 0.0),
+            ],
+            (0.4, 0.1),
+            Arc::new(Material::Lambertain(Lambertain::default())),
+        )
+        .with_mode(CurveMode::Cylinder);
+        let towards = |x: f64, y: f64| Ray::new(Point::new(x, y, -5.0), Vec3::new(0.0, 0.0, 1.0));
+
+        // top of the arch, which is 3/4 of the way up to the middle control points
+        let rec = curve.hit(&towards(1.5, 0.75), 0.001, f64::MAX).unwrap();
+        assert!((rec.t - 5.0).abs() < 1e-9);
+        assert!((rec.uv.0 - 0.5).abs() < 1e-3);
+        assert!(rec.normal.z < -0.999);
+
+        // half the width is 0.125 there, so just inside and just outside the edge
+        let rec = curve.hit(&towards(1.5, 0.86), 0.001, f64::MAX).unwrap();
+        assert!(rec.normal.y > 0.8);
+        assert!(curve.hit(&towards(1.5, 0.89), 0.001, f64::MAX).is_none());
+
+        // past the end
+        assert!(curve.hit(&towards(3.3, 0.0), 0.001, f64::MAX).is_none());
+    }
+}
@@ -0,0 +1,112 @@
+use std::{collections::HashMap, sync::Arc};
+
+use glam::DVec2;
+
+use crate::{
+    subdivision::PolyMesh,
+    texture::{Texture, TextureMat},
+    vec3::Vec3,
+};
+
+// pushes a mesh's surface out along its normals by the height in a texture
+#[derive(Clone)]
+pub struct Displacement {
+    // the height is the average of the texture's channels
+    pub texture: Arc<Texture>,
+    // distance a height of 1 moves the surface, in the mesh's own units
+    pub scale: f64,
+    // triangles are split until no edge is longer than this before they are displaced
+    pub edge_length: f64,
+}
+
+impl Displacement {
+    pub fn new(texture: Arc<Texture>, scale: f64, edge_length: f64) -> Self {
+        Self {
+            texture,
+            scale,
+            edge_length,
+        }
+    }
+}
+
+impl PolyMesh {
+    pub fn displace(&self, displacement: &Displacement) -> PolyMesh {
+        let mut mesh = self.tessellate(displacement.edge_length);
+        let normals = mesh.smooth_normals();
+
+        // vertices at the same position move together by the average height of every corner
+        // there, so seams where the uvs jump don't crack open. the normals already agree
+        let key = |p: Vec3| [p.x.to_bits(), p.y.to_bits(), p.z.to_bits()];
+        let mut heights: HashMap<[u64; 3], (f64, u32, Vec3)> = HashMap::new();
+        for (f, face) in mesh.faces.iter().enumerate() {
+            for (c, v) in face.iter().enumerate() {
+                let uv = mesh.uvs.as_ref().map_or(DVec2::ZERO, |uvs| uvs[f][c]);
+                let p = mesh.positions[*v];
+                let color = displacement.texture.value(uv.x, uv.y, &p);
+                let entry = heights.entry(key(p)).or_insert((0.0, 0, normals[f][c]));
+                entry.0 += (color.x + color.y + color.z) / 3.0;
+                entry.1 += 1;
+            }
+        }
+        for p in mesh.positions.iter_mut() {
+            if let Some((sum, count, normal)) = heights.get(&key(*p)) {
+                *p += *normal * (sum / *count as f64) * displacement.scale;
+            }
+        }
+
+        mesh.normals = Some(mesh.smooth_normals());
+        mesh
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use glam::DVec2;
+
+    use super::Displacement;
+    use crate::{
+        subdivision::PolyMesh,
+        texture::{ImageTexture, Texture},
+        vec3::Vec3,
+    };
+
+    #[test]
+    fn seams_move_together() {
+        // black on the left half of the image, white on the right
+        let path = std::env::temp_dir().join("displacement_seam.png");
+        image::RgbImage::from_fn(2, 1, |x, _| image::Rgb([255 * x as u8; 3]))
+            .save(&path)
+            .unwrap();
+        let texture = Arc::new(Texture::ImageTexture(ImageTexture::new(
+            path.display().to_string(),
+        )));
+
+        // a square cut along its diagonal, with the halves from opposite sides of the image
+        let (a, b) = (Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 0.0));
+        let mesh = PolyMesh {
+            positions: vec![
+                a,
+                Vec3::new(1.0, 0.0, 0.0),
+                b,
+                b,
+                Vec3::new(0.0, 1.0, 0.0),
+                a,
+            ],
+            faces: vec![vec![0, 1, 2], vec![3, 4, 5]],
+            uvs: Some(vec![
+                vec![DVec2::new(0.1, 0.5); 3],
+                vec![DVec2::new(0.9, 0.5); 3],
+            ]),
+            ..Default::default()
+        }
+        .displace(&Displacement::new(texture, 1.0, 10.0));
+
+        let p = &mesh.positions;
+        assert!((p[0] - p[5]).length() < 1e-9 && (p[2] - p[3]).length() < 1e-9);
+        assert!((p[0] - Vec3::new(0.0, 0.0, 0.5)).length() < 1e-9);
+        assert!((p[1] - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-9);
+        assert!((p[4] - Vec3::new(0.0, 1.0, 1.0)).length() < 1e-9);
+    }
+}
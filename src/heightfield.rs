@@ -0,0 +1,349 @@
+use std::{path::Path, sync::Arc};
+
+use crate::{
+    aabb::AABB,
+    hittable::{HitRecord, Hittable},
+    material::Material,
+    mesh::triangle_intersect,
+    ray::Ray,
+    vec3::{Point, Vec3},
+};
+
+// grid of heights spread over `size.x` by `size.z` from `corner`, with a height of 1 reaching
+// `size.y` above it. the ray walks the grid with a 2d dda, starting on a coarse level of a
+// min/max pyramid and only going down to the cells it could actually hit. each cell is two
+// triangles with smooth normals, and uv runs from 0 to 1 across x and z
+pub struct Heightfield {
+    heights: Vec<f64>,
+    // samples along x and z
+    width: usize,
+    depth: usize,
+    corner: Point,
+    size: Vec3,
+    normals: Vec<Vec3>,
+    // lowest and highest height in each cell, then in each 2x2 block of those and so on up to a
+    // single block over everything
+    levels: Vec<Level>,
+    pub mat: Arc<Material>,
+}
+
+struct Level {
+    width: usize,
+    depth: usize,
+    range: Vec<(f64, f64)>,
+}
+
+impl Heightfield {
+    // the brightness of a grayscale image. rows go from the far side to the near side, so the
+    // same image as an ImageTexture lines up with the uvs
+    pub fn new(path: String, corner: Point, size: Vec3, mat: Arc<Material>) -> Self {
+        let img = image::open(Path::new(&path))
+            .expect("Opening image")
+            .to_luma16();
+        let (width, depth) = (img.width() as usize, img.height() as usize);
+
+        let mut heights = vec![0.0; width * depth];
+        for (x, y, pixel) in img.enumerate_pixels() {
+            let z = depth - 1 - y as usize;
+            heights[z * width + x as usize] = pixel.0[0] as f64 / u16::MAX as f64;
+        }
+        Self::from_heights(heights, width, depth, corner, size, mat)
+    }
+
+    // heights row by row along x, starting from the corner
+    pub fn from_heights(
+        heights: Vec<f64>,
+        width: usize,
+        depth: usize,
+        corner: Point,
+        size: Vec3,
+        mat: Arc<Material>,
+    ) -> Self {
+        assert!(width >= 2 && depth >= 2 && heights.len() == width * depth);
+
+        let mut field = Self {
+            heights,
+            width,
+            depth,
+            corner,
+            size,
+            normals: vec![],
+            levels: vec![],
+            mat,
+        };
+        field.normals = (0..depth)
+            .flat_map(|z| (0..width).map(move |x| (x, z)))
+            .map(|(x, z)| field.sample_normal(x, z))
+            .collect();
+        field.build_levels();
+        field
+    }
+
+    fn height(&self, x: usize, z: usize) -> f64 {
+        self.heights[z * self.width + x]
+    }
+
+    fn point(&self, x: usize, z: usize) -> Point {
+        self.corner
+            + Vec3::new(
+                x as f64 / (self.width - 1) as f64 * self.size.x,
+                self.height(x, z) * self.size.y,
+                z as f64 / (self.depth - 1) as f64 * self.size.z,
+            )
+    }
+
+    // central differences, one sided at the edges
+    fn sample_normal(&self, x: usize, z: usize) -> Vec3 {
+        let (x0, x1) = (x.saturating_sub(1), (x + 1).min(self.width - 1));
+        let (z0, z1) = (z.saturating_sub(1), (z + 1).min(self.depth - 1));
+        let along_x = self.point(x1, z) - self.point(x0, z);
+        let along_z = self.point(x, z1) - self.point(x, z0);
+        along_z.cross(along_x).unit()
+    }
+
+    fn build_levels(&mut self) {
+        let (width, depth) = (self.width - 1, self.depth - 1);
+        let mut range = Vec::with_capacity(width * depth);
+        for z in 0..depth {
+            for x in 0..width {
+                let hs = [
+                    self.height(x, z),
+                    self.height(x + 1, z),
+                    self.height(x, z + 1),
+                    self.height(x + 1, z + 1),
+                ];
+                let min = hs.iter().cloned().fold(f64::INFINITY, f64::min);
+                let max = hs.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+                range.push((min, max));
+            }
+        }
+        self.levels.push(Level {
+            width,
+            depth,
+            range,
+        });
+
+        loop {
+            let below = self.levels.last().unwrap();
+            if below.width == 1 && below.depth == 1 {
+                break;
+            }
+            let (width, depth) = (below.width.div_ceil(2), below.depth.div_ceil(2));
+            let mut range = vec![(f64::INFINITY, f64::NEG_INFINITY); width * depth];
+            for z in 0..below.depth {
+                for x in 0..below.width {
+                    let (lo, hi) = below.range[z * below.width + x];
+                    let r = &mut range[(z / 2) * width + x / 2];
+                    *r = (r.0.min(lo), r.1.max(hi));
+                }
+            }
+            self.levels.push(Level {
+                width,
+                depth,
+                range,
+            });
+        }
+    }
+
+    // walks the cells of a level between t0 and t1 in the order the ray passes through them,
+    // staying inside the block of cells (x, z) to (x + n, z + n)
+    #[allow(clippy::too_many_arguments)]
+    fn march(
+        &self,
+        r: &Ray,
+        level: usize,
+        block: (usize, usize, usize),
+        t0: f64,
+        t1: f64,
+        t_min: f64,
+        t_max: f64,
+    ) -> Option<HitRecord> {
+        let grid = &self.levels[level];
+        let cell = Vec3::new(
+            self.size.x / (self.width - 1) as f64 * (1 << level) as f64,
+            0.0,
+            self.size.z / (self.depth - 1) as f64 * (1 << level) as f64,
+        );
+        let (bx, bz, n) = block;
+        let x_cells = (bx, (bx + n).min(grid.width) - 1);
+        let z_cells = (bz, (bz + n).min(grid.depth) - 1);
+
+        // start where the ray comes in, kept inside the block so a point right on its edge
+        // can't land in the cell next door
+        let start = r.at(t0) - self.corner;
+        let cell_of = |p: f64, size: f64, range: (usize, usize)| {
+            ((p / size).floor().max(0.0) as usize).clamp(range.0, range.1)
+        };
+        let mut x = cell_of(start.x, cell.x, x_cells);
+        let mut z = cell_of(start.z, cell.z, z_cells);
+
+        // dda along x and z
+        let axis = |d: f64, o: f64, size: f64, i: usize| {
+            if d > 0.0 {
+                (1, (((i + 1) as f64 * size) - o) / d, size / d)
+            } else if d < 0.0 {
+                (-1, ((i as f64 * size) - o) / d, -size / d)
+            } else {
+                (0, f64::INFINITY, f64::INFINITY)
+            }
+        };
+        let origin = r.orig - self.corner;
+        let (step_x, mut next_x, delta_x) = axis(r.dir.x, origin.x, cell.x, x);
+        let (step_z, mut next_z, delta_z) = axis(r.dir.z, origin.z, cell.z, z);
+
+        let mut enter = t0;
+        while enter <= t1 {
+            let exit = next_x.min(next_z).min(t1);
+
+            let (y0, y1) = (r.at(enter).y, r.at(exit).y);
+            let (lo, hi) = grid.range[z * grid.width + x];
+            let (lo, hi) = (
+                self.corner.y + lo * self.size.y,
+                self.corner.y + hi * self.size.y,
+            );
+            if y0.min(y1) <= hi && y0.max(y1) >= lo {
+                let hit = if level == 0 {
+                    self.cell_hit(r, x, z, t_min, t_max)
+                } else {
+                    self.march(r, level - 1, (2 * x, 2 * z, 2), enter, exit, t_min, t_max)
+                };
+                if hit.is_some() {
+                    return hit;
+                }
+            }
+
+            // on to the next cell, until the ray leaves the block
+            if next_x < next_z {
+                if step_x == 0 || (step_x < 0 && x == x_cells.0) || (step_x > 0 && x == x_cells.1) {
+                    break;
+                }
+                x = (x as i64 + step_x) as usize;
+                enter = next_x;
+                next_x += delta_x;
+            } else {
+                if step_z == 0 || (step_z < 0 && z == z_cells.0) || (step_z > 0 && z == z_cells.1) {
+                    break;
+                }
+                z = (z as i64 + step_z) as usize;
+                enter = next_z;
+                next_z += delta_z;
+            }
+        }
+        None
+    }
+
+    fn cell_hit(&self, r: &Ray, x: usize, z: usize, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let corners = [(x, z), (x + 1, z), (x + 1, z + 1), (x, z + 1)];
+        let mut closest: Option<HitRecord> = None;
+
+        for tri in [[0, 2, 1], [0, 3, 2]] {
+            let [a, b, c] = tri.map(|i| corners[i]);
+            let vs = (
+                self.point(a.0, a.1),
+                self.point(b.0, b.1),
+                self.point(c.0, c.1),
+            );
+            let (t, (u, v), _) = match triangle_intersect(r, vs) {
+                Some(hit) => hit,
+                None => continue,
+            };
+            let t_max = closest.as_ref().map_or(t_max, |rec| rec.t);
+            if t < t_min || t > t_max {
+                continue;
+            }
+
+            let w = 1.0 - u - v;
+            let normal = |(x, z): (usize, usize)| self.normals[z * self.width + x];
+            let outward_normal = (w * normal(a) + u * normal(b) + v * normal(c)).unit();
+            let p = r.at(t);
+            let local = p - self.corner;
+
+            let mut rec = HitRecord {
+                t,
+                p,
+                uv: (local.x / self.size.x, local.z / self.size.z),
+                mat: self.mat.clone(),
+                ..Default::default()
+            };
+            rec.set_face_normal(r, outward_normal);
+            closest = Some(rec);
+        }
+        closest
+    }
+}
+
+impl Hittable for Heightfield {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let (t0, t1) = self
+            .bounding_box((0.0, 0.0))?
+            .hit_interval(r, t_min, t_max)?;
+        let top = self.levels.len() - 1;
+        self.march(r, top, (0, 0, 1), t0, t1, t_min, t_max)
+    }
+
+    fn bounding_box(&self, _time: (f64, f64)) -> Option<AABB> {
+        let (lo, hi) = self.levels.last().unwrap().range[0];
+        // pad so a completely flat field still has some thickness
+        let pad = 0.0001;
+        Some(AABB::new(
+            self.corner + Vec3::new(-pad, lo * self.size.y - pad, -pad),
+            self.corner + Vec3::new(self.size.x + pad, hi * self.size.y + pad, self.size.z + pad),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::Heightfield;
+    use crate::{
+        hittable::Hittable,
+        material::{Lambertain, Material},
+        ray::Ray,
+        utils::random_double,
+        vec3::{Point, Vec3},
+    };
+
+    #[test]
+    fn walk_matches_every_cell() {
+        // odd sizes so the pyramid has partly filled blocks
+        let (width, depth) = (23, 14);
+        let heights = (0..width * depth)
+            .map(|i| ((i % width) as f64 * 0.7).sin() * ((i / width) as f64 * 0.4).cos())
+            .collect();
+        let field = Heightfield::from_heights(
+            heights,
+            width,
+            depth,
+            Point::new(-2.0, 0.0, -1.0),
+            Vec3::new(4.0, 0.5, 3.0),
+            Arc::new(Material::Lambertain(Lambertain::default())),
+        );
+
+        for _ in 0..2000 {
+            let orig = Point::new(
+                random_double(-4.0, 4.0),
+                random_double(-1.0, 2.0),
+                random_double(-3.0, 4.0),
+            );
+            let r = Ray::new(orig, Vec3::random(-1.0, 1.0));
+
+            let mut closest: Option<f64> = None;
+            for z in 0..depth - 1 {
+                for x in 0..width - 1 {
+                    let t_max = closest.unwrap_or(f64::MAX);
+                    if let Some(rec) = field.cell_hit(&r, x, z, 0.001, t_max) {
+                        closest = Some(rec.t);
+                    }
+                }
+            }
+
+            let walked = field.hit(&r, 0.001, f64::MAX).map(|rec| rec.t);
+            match (walked, closest) {
+                (Some(a), Some(b)) => assert!((a - b).abs() < 1e-9),
+                (a, b) => assert_eq!(a.is_some(), b.is_some()),
+            }
+        }
+    }
+}
@@ -3,7 +3,7 @@ use crate::{
     material::{Lambertain, Material},
     object::Object,
     ray::Ray,
-    vec3::{Point, Vec3},
+    vec3::{Color, Point, Vec3},
 };
 use std::sync::Arc;
 #[derive(Clone)]
@@ -14,6 +14,12 @@ pub struct HitRecord {
     pub uv: (f64, f64),
     pub front_face: bool,
     pub mat: Arc<Material>,
+    // how p changes along u and v, used to orient normal and bump maps. zero when the surface
+    // doesn't have them
+    pub dpdu: Vec3,
+    pub dpdv: Vec3,
+    // interpolated from the vertices of meshes that have colours, for VertexColor textures
+    pub color: Option<Color>,
 }
 
 impl HitRecord {
@@ -36,6 +42,9 @@ impl Default for HitRecord {
             front_face: false,
             uv: (0.0, 0.0),
             mat: Arc::new(Material::Lambertain(Lambertain::default())),
+            dpdu: Vec3::default(),
+            dpdv: Vec3::default(),
+            color: None,
         }
     }
 }
@@ -52,6 +61,27 @@ pub trait Hittable {
     }
 }
 
+// pdf of the direction v from o when a point is picked uniformly over a surface of the given area.
+// sums over every crossing along the ray, as any of them could have been the sampled point
+pub fn area_pdf_value(h: &impl Hittable, area: f64, o: &Point, v: &Vec3) -> f64 {
+    let r = Ray::new(*o, *v);
+    let mut pdf = 0.0;
+    let mut t_min = 0.001;
+    // nothing here is crossed more than four times
+    for _ in 0..4 {
+        match h.hit(&r, t_min, f64::MAX) {
+            None => break,
+            Some(rec) => {
+                let dist_sqrd = rec.t * rec.t * v.length_squared();
+                let cos = (v.dot(rec.normal)).abs() / v.length();
+                pdf += dist_sqrd / (cos * area);
+                t_min = rec.t + 0.001;
+            }
+        }
+    }
+    pdf
+}
+
 pub struct FlipFace {
     pub ptr: Arc<Object>,
 }
@@ -95,15 +125,9 @@ pub struct MatTransform {
 
 impl MatTransform {
     pub fn new(mat: glam::DMat4, ptr: Arc<Object>) -> Self {
-        let aabb_ptr = ptr.bounding_box((0.0, 0.0));
-
-        let aabb = match aabb_ptr {
-            None => None,
-            Some(aabb) => Some(AABB {
-                min: mat.transform_point3(aabb.min.into()).into(),
-                max: mat.transform_point3(aabb.max.into()).into(),
-            }),
-        };
+        let aabb = ptr
+            .bounding_box((0.0, 0.0))
+            .map(|aabb| aabb.transform(&mat));
 
         Self {
             mat_i: mat.inverse(),
@@ -112,6 +136,76 @@ impl MatTransform {
             aabb,
         }
     }
+
+    // sized and moved by the object's own bounds rather than a hand tuned matrix
+    pub fn placed(ptr: Arc<Object>, placement: &Placement) -> Self {
+        let mat = placement.matrix(&ptr);
+        Self::new(mat, ptr)
+    }
+}
+
+// where to put an object going by its bounds, for models that come in at any size, position and
+// orientation
+#[derive(Clone, Copy)]
+pub struct Placement {
+    // the model's up direction, turned to point along +y
+    pub up: Vec3,
+    // degrees about +y once it's upright
+    pub rotation: f64,
+    // size of the box the model is scaled to fit, keeping its proportions. an infinite side
+    // leaves that axis free, none keeps the model's own size
+    pub fit: Option<Vec3>,
+    // where the centre of the bounds goes, or the centre of their base when on_ground is set
+    pub position: Point,
+    pub on_ground: bool,
+}
+
+impl Default for Placement {
+    fn default() -> Self {
+        Self {
+            up: Vec3::new(0.0, 1.0, 0.0),
+            rotation: 0.0,
+            fit: None,
+            position: Point::default(),
+            on_ground: false,
+        }
+    }
+}
+
+impl Placement {
+    pub fn matrix(&self, ptr: &Object) -> glam::DMat4 {
+        let turn = glam::DQuat::from_rotation_y(self.rotation.to_radians())
+            * glam::DQuat::from_rotation_arc(self.up.unit().into(), glam::DVec3::Y);
+        let turned = glam::DMat4::from_quat(turn);
+
+        // meshes go by their vertices, which fit tighter than a turned box
+        let bx = match ptr {
+            Object::TriangleMesh(tm) => tm.mesh.bounds(&turned),
+            _ => ptr.bounding_box((0.0, 0.0)).map(|bx| bx.transform(&turned)),
+        };
+        let bx = match bx {
+            Some(bx) => bx,
+            None => return glam::DMat4::IDENTITY,
+        };
+
+        let extent = bx.max - bx.min;
+        let scale = match self.fit {
+            Some(size) => (size.x / extent.x)
+                .min(size.y / extent.y)
+                .min(size.z / extent.z),
+            None => 1.0,
+        };
+        let mut anchor = 0.5 * (bx.min + bx.max);
+        if self.on_ground {
+            anchor.y = bx.min.y;
+        }
+
+        glam::DMat4::from_scale_rotation_translation(
+            glam::DVec3::ONE * scale,
+            turn,
+            (self.position - scale * anchor).into(),
+        )
+    }
 }
 
 impl Hittable for MatTransform {
@@ -120,52 +214,257 @@ impl Hittable for MatTransform {
     }
 
     fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
-        let osd = self.mat_i.transform_vector3(r.dir.into());
+        transformed_hit(&self.mat, &self.mat_i, &self.ptr, r, t_min, t_max)
+    }
 
-        let oso = self.mat_i.transform_point3(r.orig.into());
+    fn pdf_value(&self, o: &Point, v: &Point) -> f64 {
+        transformed_pdf_value(&self.mat, &self.mat_i, &self.ptr, o, v)
+        // self.ptr.pdf_value(o, v)
+    }
 
-        let object_space_ray = Ray::new(
-            Vec3::new(oso.x, oso.y, oso.z),
-            Vec3::new(osd.x, osd.y, osd.z),
-        );
+    fn random(&self, o: &Vec3) -> Vec3 {
+        transformed_random(&self.mat, &self.mat_i, &self.ptr, o)
+        // self.ptr.random(o)
+    }
+}
 
-        match self.ptr.hit(&object_space_ray, t_min, t_max) {
-            None => None,
-            Some(rec) => {
-                let world_space_mat = self.mat_i.transpose();
-                let world_normal =
-                    Vec3::from(world_space_mat.transform_vector3(rec.normal.into())).unit();
-                let world_p = Vec3::from(self.mat.transform_point3(rec.p.into()));
-
-                Some(HitRecord {
-                    p: world_p,
-                    normal: world_normal,
-                    t: rec.t,
-                    uv: rec.uv,
-                    front_face: rec.front_face,
-                    mat: rec.mat,
-                })
-            }
+// a transform that moves between two matrices over the shutter interval, for motion blur.
+// scale and translation are lerped and rotation slerped by ray time
+pub struct MotionTransform {
+    start: (glam::DVec3, glam::DQuat, glam::DVec3),
+    end: (glam::DVec3, glam::DQuat, glam::DVec3),
+    time: (f64, f64),
+    // the matrix and its inverse half way through, for light sampling
+    middle: (glam::DMat4, glam::DMat4),
+    pub aabb: Option<AABB>,
+    pub ptr: Arc<Object>,
+}
+
+impl MotionTransform {
+    pub fn new(start: glam::DMat4, end: glam::DMat4, time: (f64, f64), ptr: Arc<Object>) -> Self {
+        let mut mt = Self {
+            start: start.to_scale_rotation_translation(),
+            end: end.to_scale_rotation_translation(),
+            time,
+            middle: (glam::DMat4::IDENTITY, glam::DMat4::IDENTITY),
+            aabb: None,
+            ptr,
+        };
+        mt.middle = mt.matrices(0.5 * (time.0 + time.1));
+        mt.aabb = mt.swept_bounding_box();
+        mt
+    }
+
+    pub fn matrix(&self, time: f64) -> glam::DMat4 {
+        self.matrices(time).0
+    }
+
+    // the matrix at `time` and its inverse, which is built straight from the inverted parts
+    // rather than by inverting the matrix on every ray
+    fn matrices(&self, time: f64) -> (glam::DMat4, glam::DMat4) {
+        let t = if self.time.1 > self.time.0 {
+            ((time - self.time.0) / (self.time.1 - self.time.0)).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+
+        let scale = self.start.0.lerp(self.end.0, t);
+        let rotation = self.start.1.slerp(self.end.1, t);
+        let translation = self.start.2.lerp(self.end.2, t);
+
+        let mat = glam::DMat4::from_scale_rotation_translation(scale, rotation, translation);
+        let mat_i = glam::DMat4::from_scale(scale.recip())
+            * glam::DMat4::from_quat(rotation.conjugate())
+            * glam::DMat4::from_translation(-translation);
+        (mat, mat_i)
+    }
+
+    // union of the child's box at evenly spaced steps, padded by how far a corner can stray
+    // from the straight line between two steps while rotating
+    fn swept_bounding_box(&self) -> Option<AABB> {
+        const STEPS: usize = 32;
+
+        let bx = self.ptr.bounding_box(self.time)?;
+
+        let mut swept = bx.transform(&self.matrix(self.time.0));
+        for i in 1..=STEPS {
+            let time = self.time.0 + (self.time.1 - self.time.0) * i as f64 / STEPS as f64;
+            swept = AABB::from_surrounding(swept, bx.transform(&self.matrix(time)));
         }
+
+        let theta = 2.0 * self.start.1.dot(self.end.1).abs().min(1.0).acos();
+        let max_scale = self.start.0.max(self.end.0).max_element();
+        let scale_change = (self.end.0 - self.start.0).abs().max_element();
+        let radius = bx.corners().iter().map(|c| c.length()).fold(0.0, f64::max);
+        let pad = (theta * theta * max_scale + 2.0 * theta * scale_change) * radius
+            / (8.0 * (STEPS * STEPS) as f64);
+        let pad = Vec3::new(pad, pad, pad);
+
+        Some(AABB::new(swept.min - pad, swept.max + pad))
     }
+}
 
-    fn pdf_value(&self, o: &Point, v: &Point) -> f64 {
-        let object_o = Vec3::from(self.mat_i.transform_point3((*o).into()));
-        let object_v = Vec3::from(self.mat_i.transform_vector3((*v).into()));
+impl Hittable for MotionTransform {
+    fn bounding_box(&self, _time: (f64, f64)) -> Option<AABB> {
+        self.aabb
+    }
 
-        let (s, _, _) = self.mat.to_scale_rotation_translation();
-        let m = s.max_element();
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let (mat, mat_i) = self.matrices(r.time);
+        transformed_hit(&mat, &mat_i, &self.ptr, r, t_min, t_max)
+    }
 
-        self.ptr.pdf_value(&object_o, &object_v) * m
-        // self.ptr.pdf_value(o, v)
+    fn pdf_value(&self, o: &Point, v: &Point) -> f64 {
+        let (mat, mat_i) = &self.middle;
+        transformed_pdf_value(mat, mat_i, &self.ptr, o, v)
     }
 
     fn random(&self, o: &Vec3) -> Vec3 {
-        let object_o = Vec3::from(self.mat_i.transform_point3((*o).into()));
-        Vec3::from(
-            self.mat
-                .transform_vector3(self.ptr.random(&object_o).into()),
-        )
-        // self.ptr.random(o)
+        let (mat, mat_i) = &self.middle;
+        transformed_random(mat, mat_i, &self.ptr, o)
+    }
+}
+
+fn transformed_hit(
+    mat: &glam::DMat4,
+    mat_i: &glam::DMat4,
+    ptr: &Object,
+    r: &Ray,
+    t_min: f64,
+    t_max: f64,
+) -> Option<HitRecord> {
+    ptr.hit(&object_space_ray(mat_i, r), t_min, t_max)
+        .map(|rec| world_space_record(mat, mat_i, rec))
+}
+
+// the ray in the space of an object placed by mat. t is the same along both rays
+pub(crate) fn object_space_ray(mat_i: &glam::DMat4, r: &Ray) -> Ray {
+    let osd = mat_i.transform_vector3(r.dir.into());
+
+    let oso = mat_i.transform_point3(r.orig.into());
+
+    Ray::new(
+        Vec3::new(oso.x, oso.y, oso.z),
+        Vec3::new(osd.x, osd.y, osd.z),
+    )
+    .with_time(r.time)
+}
+
+pub(crate) fn world_space_record(
+    mat: &glam::DMat4,
+    mat_i: &glam::DMat4,
+    rec: HitRecord,
+) -> HitRecord {
+    let world_space_mat = mat_i.transpose();
+    let world_normal = Vec3::from(world_space_mat.transform_vector3(rec.normal.into())).unit();
+    let world_p = Vec3::from(mat.transform_point3(rec.p.into()));
+
+    HitRecord {
+        p: world_p,
+        normal: world_normal,
+        t: rec.t,
+        uv: rec.uv,
+        front_face: rec.front_face,
+        mat: rec.mat,
+        dpdu: Vec3::from(mat.transform_vector3(rec.dpdu.into())),
+        dpdv: Vec3::from(mat.transform_vector3(rec.dpdv.into())),
+        color: rec.color,
+    }
+}
+
+fn transformed_pdf_value(
+    mat: &glam::DMat4,
+    mat_i: &glam::DMat4,
+    ptr: &Object,
+    o: &Point,
+    v: &Point,
+) -> f64 {
+    let object_o = Vec3::from(mat_i.transform_point3((*o).into()));
+    let object_v = Vec3::from(mat_i.transform_vector3((*v).into()));
+
+    let (s, _, _) = mat.to_scale_rotation_translation();
+    let m = s.max_element();
+
+    ptr.pdf_value(&object_o, &object_v) * m
+}
+
+fn transformed_random(mat: &glam::DMat4, mat_i: &glam::DMat4, ptr: &Object, o: &Vec3) -> Vec3 {
+    let object_o = Vec3::from(mat_i.transform_point3((*o).into()));
+    Vec3::from(mat.transform_vector3(ptr.random(&object_o).into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{MatTransform, MotionTransform, Placement};
+    use crate::{
+        hittable::Hittable,
+        material::{Lambertain, Material},
+        object::Object,
+        ray::Ray,
+        rect::RectBox,
+        sphere::Sphere,
+        vec3::{Point, Vec3},
+    };
+
+    #[test]
+    fn placement_stands_up_fits_and_grounds() {
+        let mat = Arc::new(Material::Lambertain(Lambertain::default()));
+        let model = Arc::new(Object::RectBox(RectBox::new(
+            &Point::new(0.0, 0.0, 0.0),
+            &Point::new(1.0, 2.0, 3.0),
+            mat,
+        )));
+
+        // z up, so the 3 long side ends up as the height and gets scaled to 6
+        let placement = Placement {
+            up: Vec3::new(0.0, 0.0, 1.0),
+            fit: Some(Vec3::new(f64::INFINITY, 6.0, f64::INFINITY)),
+            position: Point::new(10.0, 0.0, 0.0),
+            on_ground: true,
+            ..Default::default()
+        };
+        let bx = MatTransform::placed(model, &placement)
+            .bounding_box((0.0, 0.0))
+            .unwrap();
+        assert!((bx.min - Point::new(9.0, 0.0, -2.0)).length() < 1e-9);
+        assert!((bx.max - Point::new(11.0, 6.0, 2.0)).length() < 1e-9);
+    }
+
+    #[test]
+    fn moving_instance_is_where_the_ray_time_puts_it() {
+        let mat = Arc::new(Material::Lambertain(Lambertain::default()));
+        let ball = Arc::new(Object::Sphere(Sphere::new(Point::default(), 1.0, mat)));
+        let start = glam::DMat4::IDENTITY;
+        let end = glam::DMat4::from_scale_rotation_translation(
+            glam::DVec3::splat(2.0),
+            glam::DQuat::from_rotation_z(1.0),
+            glam::DVec3::new(10.0, 0.0, 0.0),
+        );
+        let moving = MotionTransform::new(start, end, (0.0, 1.0), ball);
+
+        let (m, m_i) = moving.matrices(0.3);
+        assert!((m * m_i).abs_diff_eq(glam::DMat4::IDENTITY, 1e-12));
+        assert!(m_i.abs_diff_eq(m.inverse(), 1e-12));
+
+        // at time 0.5 the ball is at x = 5 with radius 1.5
+        let down = |x: f64, time: f64| {
+            Ray::new(Point::new(x, 0.0, 10.0), Vec3::new(0.0, 0.0, -1.0)).with_time(time)
+        };
+        let rec = moving.hit(&down(5.0, 0.5), 0.001, f64::INFINITY).unwrap();
+        assert!((rec.p - Point::new(5.0, 0.0, 1.5)).length() < 1e-9);
+        assert!((rec.normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-9);
+        assert!(moving.hit(&down(5.0, 0.0), 0.001, f64::INFINITY).is_none());
+        assert!(moving.hit(&down(0.0, 0.5), 0.001, f64::INFINITY).is_none());
+
+        let bx = moving.bounding_box((0.0, 1.0)).unwrap();
+        for i in 0..=20 {
+            let time = i as f64 / 20.0;
+            let at = moving.matrix(time).transform_point3(glam::DVec3::ZERO);
+            let r = 1.0 + time;
+            assert!(bx.min.x <= at.x - r && bx.min.y <= at.y - r && bx.min.z <= at.z - r);
+            assert!(bx.max.x >= at.x + r && bx.max.y >= at.y + r && bx.max.z >= at.z + r);
+        }
     }
 }
@@ -0,0 +1,608 @@
+use std::error::Error;
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+};
+
+use crate::{
+    camera::Shutter,
+    ray::Ray,
+    utils::random_double_normal,
+    vec3::{Point, Vec3},
+};
+
+// one row of a lens prescription. a curvature radius of 0 marks the aperture stop
+#[derive(Clone, Copy)]
+pub struct LensElement {
+    pub curvature_radius: f64,
+    pub thickness: f64,
+    pub eta: f64,
+    pub aperture_radius: f64,
+}
+
+// {radius} {thickness} {ior} {aperture diameter}, one element per line, front element first.
+// lines starting with # are comments
+pub fn load_lens(path: String) -> Result<Vec<LensElement>, Box<dyn Error>> {
+    let file = File::open(path)?;
+    let reader = BufReader::new(file);
+
+    let mut elements = vec![];
+
+    for line in reader.lines() {
+        let str = line?;
+        let str = str.split('#').next().unwrap_or("");
+        let parts = str
+            .split_whitespace()
+            .map(|s| s.parse::<f64>())
+            .collect::<Result<Vec<f64>, _>>()?;
+        if parts.is_empty() {
+            continue;
+        }
+        if parts.len() != 4 {
+            return Err(format!("lens element needs 4 columns, got {}", parts.len()).into());
+        }
+        elements.push(LensElement {
+            curvature_radius: parts[0],
+            thickness: parts[1],
+            eta: parts[2],
+            aperture_radius: parts[3] / 2.0,
+        });
+    }
+
+    if elements.is_empty() {
+        return Err("lens has no elements".into());
+    }
+
+    Ok(elements)
+}
+
+#[derive(Clone, Copy)]
+struct PupilBounds {
+    min: (f64, f64),
+    max: (f64, f64),
+}
+
+impl PupilBounds {
+    fn from_point(x: f64, y: f64) -> Self {
+        Self {
+            min: (x, y),
+            max: (x, y),
+        }
+    }
+
+    fn union(&self, x: f64, y: f64) -> Self {
+        Self {
+            min: (self.min.0.min(x), self.min.1.min(y)),
+            max: (self.max.0.max(x), self.max.1.max(y)),
+        }
+    }
+
+    fn inside(&self, x: f64, y: f64) -> bool {
+        x >= self.min.0 && x <= self.max.0 && y >= self.min.1 && y <= self.max.1
+    }
+
+    fn expand(&self, d: f64) -> Self {
+        Self {
+            min: (self.min.0 - d, self.min.1 - d),
+            max: (self.max.0 + d, self.max.1 + d),
+        }
+    }
+
+    fn diagonal(&self) -> f64 {
+        let dx = self.max.0 - self.min.0;
+        let dy = self.max.1 - self.min.1;
+        (dx * dx + dy * dy).sqrt()
+    }
+
+    fn area(&self) -> f64 {
+        (self.max.0 - self.min.0) * (self.max.1 - self.min.1)
+    }
+}
+
+const PUPIL_INTERVALS: usize = 64;
+const PUPIL_SAMPLES: usize = 128 * 128;
+
+// traces rays from the film through a stack of spherical lens elements, after pbrt's
+// RealisticCamera. the film sits at the camera origin and the lens extends towards lookat
+pub struct RealisticCamera {
+    origin: Point,
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+    elements: Vec<LensElement>,
+    film_size: (f64, f64),
+    film_diagonal: f64,
+    exit_pupil_bounds: Vec<PupilBounds>,
+    axial_area: f64,
+    time: (f64, f64),
+    shutter: Shutter,
+}
+
+impl RealisticCamera {
+    // `scale` converts the prescription and film diagonal (both in mm) into scene units.
+    // `focus_dist` is measured from the film, and is an error if it's closer than the lens
+    // can focus
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        lookfrom: Point,
+        lookat: Point,
+        vup: Vec3,
+        elements: Vec<LensElement>,
+        scale: f64,
+        film_diagonal: f64,
+        aspect_ratio: f64,
+        focus_dist: f64,
+    ) -> Result<Self, Box<dyn Error>> {
+        let w = (lookat - lookfrom).unit();
+        let u = w.cross(vup).unit();
+        let v = u.cross(w);
+
+        let elements = elements
+            .iter()
+            .map(|e| LensElement {
+                curvature_radius: e.curvature_radius * scale,
+                thickness: e.thickness * scale,
+                eta: e.eta,
+                aperture_radius: e.aperture_radius * scale,
+            })
+            .collect();
+
+        let film_diagonal = film_diagonal * scale;
+        let film_height = film_diagonal / (1.0 + aspect_ratio * aspect_ratio).sqrt();
+
+        let mut camera = Self {
+            origin: lookfrom,
+            u,
+            v,
+            w,
+            elements,
+            film_size: (aspect_ratio * film_height, film_height),
+            film_diagonal,
+            exit_pupil_bounds: vec![],
+            axial_area: 1.0,
+            time: (0.0, 0.0),
+            shutter: Shutter::default(),
+        };
+
+        let rear_thickness = camera.focus_thick_lens(focus_dist)?;
+        camera.elements.last_mut().unwrap().thickness = rear_thickness;
+
+        let max_r = camera.film_diagonal / 2.0;
+        camera.exit_pupil_bounds = (0..PUPIL_INTERVALS)
+            .map(|i| {
+                let r0 = i as f64 / PUPIL_INTERVALS as f64 * max_r;
+                let r1 = (i + 1) as f64 / PUPIL_INTERVALS as f64 * max_r;
+                camera.bound_exit_pupil(r0, r1)
+            })
+            .collect();
+        camera.axial_area = camera.exit_pupil_bounds[0].area();
+
+        Ok(camera)
+    }
+
+    pub fn with_time(mut self, t0: f64, t1: f64) -> Self {
+        self.time = (t0, t1);
+        self
+    }
+
+    pub fn with_shutter(mut self, shutter: Shutter) -> Self {
+        self.shutter = shutter;
+        self
+    }
+
+    // returns the ray leaving the front element along with its vignetting weight, or None
+    // if the lens blocked it
+    pub fn generate_ray(&self, s: f64, t: f64) -> Option<(Ray, f64)> {
+        // the lens flips the image, so the film is sampled upside-down and back-to-front
+        let p_film = Point::new(
+            -(s - 0.5) * self.film_size.0,
+            -(t - 0.5) * self.film_size.1,
+            0.0,
+        );
+
+        let (p_rear, area) = self.sample_exit_pupil(
+            p_film.x,
+            p_film.y,
+            random_double_normal(),
+            random_double_normal(),
+        );
+        let r_film = Ray::new(p_film, p_rear - p_film);
+        let r = self.trace_lenses_from_film(&r_film)?;
+
+        let cos_theta = r_film.dir.unit().z;
+        let weight = cos_theta.powi(4) * area / self.axial_area;
+
+        let ray = Ray::new(
+            self.origin + self.u * r.orig.x + self.v * r.orig.y + self.w * r.orig.z,
+            self.u * r.dir.x + self.v * r.dir.y + self.w * r.dir.z,
+        )
+        .with_time(self.shutter.sample_time(self.time, t));
+
+        Some((ray, weight))
+    }
+
+    fn lens_rear_z(&self) -> f64 {
+        self.elements.last().unwrap().thickness
+    }
+
+    fn lens_front_z(&self) -> f64 {
+        self.elements.iter().map(|e| e.thickness).sum()
+    }
+
+    fn rear_element_radius(&self) -> f64 {
+        self.elements.last().unwrap().aperture_radius
+    }
+
+    // camera space has the film at z = 0 and the lens along +z, lens space mirrors that
+    fn trace_lenses_from_film(&self, r_camera: &Ray) -> Option<Ray> {
+        let mut element_z = 0.0;
+        let mut r_lens = Ray::new(flip_z(r_camera.orig), flip_z(r_camera.dir));
+
+        for i in (0..self.elements.len()).rev() {
+            let element = &self.elements[i];
+            element_z -= element.thickness;
+
+            let is_stop = element.curvature_radius == 0.0;
+            let (t, n) = if is_stop {
+                ((element_z - r_lens.orig.z) / r_lens.dir.z, Vec3::default())
+            } else {
+                let z_center = element_z + element.curvature_radius;
+                intersect_spherical_element(element.curvature_radius, z_center, &r_lens)?
+            };
+            if t < 0.0 {
+                return None;
+            }
+
+            let p_hit = r_lens.at(t);
+            if p_hit.x * p_hit.x + p_hit.y * p_hit.y
+                > element.aperture_radius * element.aperture_radius
+            {
+                return None;
+            }
+            r_lens.orig = p_hit;
+
+            if !is_stop {
+                let eta_i = element.eta;
+                let eta_t = if i > 0 && self.elements[i - 1].eta != 0.0 {
+                    self.elements[i - 1].eta
+                } else {
+                    1.0
+                };
+                r_lens.dir = refract(-r_lens.dir.unit(), n, eta_i / eta_t)?;
+            }
+        }
+
+        Some(Ray::new(flip_z(r_lens.orig), flip_z(r_lens.dir)))
+    }
+
+    fn trace_lenses_from_scene(&self, r_camera: &Ray) -> Option<Ray> {
+        let mut element_z = -self.lens_front_z();
+        let mut r_lens = Ray::new(flip_z(r_camera.orig), flip_z(r_camera.dir));
+
+        for i in 0..self.elements.len() {
+            let element = &self.elements[i];
+
+            let is_stop = element.curvature_radius == 0.0;
+            let (t, n) = if is_stop {
+                ((element_z - r_lens.orig.z) / r_lens.dir.z, Vec3::default())
+            } else {
+                let z_center = element_z + element.curvature_radius;
+                intersect_spherical_element(element.curvature_radius, z_center, &r_lens)?
+            };
+            if t < 0.0 {
+                return None;
+            }
+
+            let p_hit = r_lens.at(t);
+            if p_hit.x * p_hit.x + p_hit.y * p_hit.y
+                > element.aperture_radius * element.aperture_radius
+            {
+                return None;
+            }
+            r_lens.orig = p_hit;
+
+            if !is_stop {
+                let eta_i = if i == 0 || self.elements[i - 1].eta == 0.0 {
+                    1.0
+                } else {
+                    self.elements[i - 1].eta
+                };
+                let eta_t = if element.eta != 0.0 { element.eta } else { 1.0 };
+                r_lens.dir = refract(-r_lens.dir.unit(), n, eta_i / eta_t)?;
+            }
+
+            element_z += element.thickness;
+        }
+
+        Some(Ray::new(flip_z(r_lens.orig), flip_z(r_lens.dir)))
+    }
+
+    // principal plane and focal point z for the scene side [0] and the film side [1]
+    fn compute_thick_lens_approximation(&self) -> Result<([f64; 2], [f64; 2]), Box<dyn Error>> {
+        let x = 0.001 * self.film_diagonal;
+
+        let r_scene = Ray::new(
+            Point::new(x, 0.0, self.lens_front_z() + 1.0),
+            Vec3::new(0.0, 0.0, -1.0),
+        );
+        let r_film = self
+            .trace_lenses_from_scene(&r_scene)
+            .ok_or("paraxial ray blocked by the lens")?;
+        let (pz0, fz0) = compute_cardinal_points(&r_scene, &r_film);
+
+        let r_film = Ray::new(
+            Point::new(x, 0.0, self.lens_rear_z() - 1.0),
+            Vec3::new(0.0, 0.0, 1.0),
+        );
+        let r_scene = self
+            .trace_lenses_from_film(&r_film)
+            .ok_or("paraxial ray blocked by the lens")?;
+        let (pz1, fz1) = compute_cardinal_points(&r_film, &r_scene);
+
+        Ok(([pz0, pz1], [fz0, fz1]))
+    }
+
+    // the rear element thickness that puts the plane at `focus_dist` in focus on the film
+    fn focus_thick_lens(&self, focus_dist: f64) -> Result<f64, Box<dyn Error>> {
+        let (pz, fz) = self.compute_thick_lens_approximation()?;
+        let f = fz[0] - pz[0];
+        let z = -focus_dist;
+        let c = (pz[1] - z - pz[0]) * (pz[1] - z - 4.0 * f - pz[0]);
+        if c <= 0.0 {
+            return Err(format!("focus distance {focus_dist} is too short for this lens").into());
+        }
+        let delta = 0.5 * (pz[1] - z + pz[0] - c.sqrt());
+        Ok(self.lens_rear_z() + delta)
+    }
+
+    // bounds on the rear element of everything that makes it through the lens from film
+    // points at radius r0..r1
+    fn bound_exit_pupil(&self, r0: f64, r1: f64) -> PupilBounds {
+        let rear_radius = self.rear_element_radius();
+        let proj_rear_bounds = PupilBounds {
+            min: (-1.5 * rear_radius, -1.5 * rear_radius),
+            max: (1.5 * rear_radius, 1.5 * rear_radius),
+        };
+
+        let mut pupil_bounds: Option<PupilBounds> = None;
+        for i in 0..PUPIL_SAMPLES {
+            let p_film = Point::new(
+                lerp((i as f64 + 0.5) / PUPIL_SAMPLES as f64, r0, r1),
+                0.0,
+                0.0,
+            );
+            let p_rear = Point::new(
+                lerp(
+                    radical_inverse(2, i),
+                    proj_rear_bounds.min.0,
+                    proj_rear_bounds.max.0,
+                ),
+                lerp(
+                    radical_inverse(3, i),
+                    proj_rear_bounds.min.1,
+                    proj_rear_bounds.max.1,
+                ),
+                self.lens_rear_z(),
+            );
+
+            let inside = match pupil_bounds {
+                Some(b) => b.inside(p_rear.x, p_rear.y),
+                None => false,
+            };
+            if inside
+                || self
+                    .trace_lenses_from_film(&Ray::new(p_film, p_rear - p_film))
+                    .is_some()
+            {
+                pupil_bounds = Some(match pupil_bounds {
+                    Some(b) => b.union(p_rear.x, p_rear.y),
+                    None => PupilBounds::from_point(p_rear.x, p_rear.y),
+                });
+            }
+        }
+
+        match pupil_bounds {
+            None => proj_rear_bounds,
+            Some(b) => b.expand(2.0 * proj_rear_bounds.diagonal() / (PUPIL_SAMPLES as f64).sqrt()),
+        }
+    }
+
+    fn sample_exit_pupil(&self, x: f64, y: f64, u1: f64, u2: f64) -> (Point, f64) {
+        let r_film = (x * x + y * y).sqrt();
+        let idx = (r_film / (self.film_diagonal / 2.0) * PUPIL_INTERVALS as f64) as usize;
+        let bounds = self.exit_pupil_bounds[idx.min(PUPIL_INTERVALS - 1)];
+
+        let p_lens = (
+            lerp(u1, bounds.min.0, bounds.max.0),
+            lerp(u2, bounds.min.1, bounds.max.1),
+        );
+
+        // the bounds were found along +x, rotate them round to the film point
+        let (sin_theta, cos_theta) = if r_film != 0.0 {
+            (y / r_film, x / r_film)
+        } else {
+            (0.0, 1.0)
+        };
+
+        (
+            Point::new(
+                cos_theta * p_lens.0 - sin_theta * p_lens.1,
+                sin_theta * p_lens.0 + cos_theta * p_lens.1,
+                self.lens_rear_z(),
+            ),
+            bounds.area(),
+        )
+    }
+}
+
+fn flip_z(v: Vec3) -> Vec3 {
+    Vec3::new(v.x, v.y, -v.z)
+}
+
+fn lerp(t: f64, a: f64, b: f64) -> f64 {
+    (1.0 - t) * a + t * b
+}
+
+fn radical_inverse(base: usize, mut i: usize) -> f64 {
+    let inv_base = 1.0 / base as f64;
+    let mut inv = inv_base;
+    let mut result = 0.0;
+    while i > 0 {
+        result += (i % base) as f64 * inv;
+        i /= base;
+        inv *= inv_base;
+    }
+    result
+}
+
+fn intersect_spherical_element(radius: f64, z_center: f64, r: &Ray) -> Option<(f64, Vec3)> {
+    let o = r.orig - Vec3::new(0.0, 0.0, z_center);
+    let a = r.dir.length_squared();
+    let b = 2.0 * r.dir.dot(o);
+    let c = o.length_squared() - radius * radius;
+
+    let disc = b * b - 4.0 * a * c;
+    if disc < 0.0 {
+        return None;
+    }
+    let sqrtd = disc.sqrt();
+    let q = if b < 0.0 {
+        -0.5 * (b - sqrtd)
+    } else {
+        -0.5 * (b + sqrtd)
+    };
+    let (t0, t1) = (q / a, c / q);
+
+    let use_closer = (r.dir.z > 0.0) ^ (radius < 0.0);
+    let t = if use_closer { t0.min(t1) } else { t0.max(t1) };
+    if t < 0.0 {
+        return None;
+    }
+
+    let n = (o + t * r.dir).unit();
+    let n = if n.dot(-r.dir) < 0.0 { -n } else { n };
+
+    Some((t, n))
+}
+
+// wi points away from the surface, on the same side as n
+fn refract(wi: Vec3, n: Vec3, eta: f64) -> Option<Vec3> {
+    let cos_theta_i = n.dot(wi);
+    let sin2_theta_i = (1.0 - cos_theta_i * cos_theta_i).max(0.0);
+    let sin2_theta_t = eta * eta * sin2_theta_i;
+    if sin2_theta_t >= 1.0 {
+        return None;
+    }
+    let cos_theta_t = (1.0 - sin2_theta_t).sqrt();
+    Some(eta * -wi + (eta * cos_theta_i - cos_theta_t) * n)
+}
+
+fn compute_cardinal_points(r_in: &Ray, r_out: &Ray) -> (f64, f64) {
+    let tf = -r_out.orig.x / r_out.dir.x;
+    let fz = -r_out.at(tf).z;
+    let tp = (r_in.orig.x - r_out.orig.x) / r_out.dir.x;
+    let pz = -r_out.at(tp).z;
+    (pz, fz)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LensElement, RealisticCamera, PUPIL_INTERVALS};
+    use crate::{
+        ray::Ray,
+        vec3::{Point, Vec3},
+    };
+
+    // data/dgauss.50mm.dat
+    const DGAUSS: [(f64, f64, f64, f64); 11] = [
+        (29.475, 3.76, 1.67, 25.2),
+        (84.83, 0.12, 1.0, 25.2),
+        (19.275, 4.025, 1.67, 23.0),
+        (40.77, 3.275, 1.699, 23.0),
+        (12.75, 5.705, 1.0, 18.0),
+        (0.0, 4.5, 0.0, 17.1),
+        (-14.495, 1.18, 1.603, 17.0),
+        (40.77, 6.065, 1.658, 20.0),
+        (-20.385, 0.19, 1.0, 20.0),
+        (437.065, 3.22, 1.717, 20.0),
+        (-39.73, 0.0, 1.0, 20.0),
+    ];
+
+    fn camera(focus_dist: f64) -> Result<RealisticCamera, Box<dyn std::error::Error>> {
+        let elements = DGAUSS
+            .iter()
+            .map(|&(radius, thickness, eta, diameter)| LensElement {
+                curvature_radius: radius,
+                thickness,
+                eta,
+                aperture_radius: diameter / 2.0,
+            })
+            .collect();
+        RealisticCamera::new(
+            Point::default(),
+            Point::new(0.0, 0.0, 1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            elements,
+            1.0,
+            35.0,
+            1.0,
+            focus_dist,
+        )
+    }
+
+    // where a ray from the point on the axis `dist` in front of the film lands on it
+    fn film_x(camera: &RealisticCamera, dist: f64) -> f64 {
+        let p = Point::new(0.0, 0.0, dist);
+        let front = Point::new(0.5, 0.0, camera.lens_front_z());
+        let r = camera
+            .trace_lenses_from_scene(&Ray::new(p, front - p))
+            .unwrap();
+        r.at(-r.orig.z / r.dir.z).x
+    }
+
+    #[test]
+    fn thick_lens_focuses_at_the_distance() {
+        let near = camera(300.0).unwrap();
+        let far = camera(1000.0).unwrap();
+        assert!(near.lens_rear_z() > far.lens_rear_z());
+
+        assert!(film_x(&near, 300.0).abs() < 0.001);
+        assert!(film_x(&far, 1000.0).abs() < 0.001);
+        assert!(film_x(&far, 300.0).abs() > 0.05);
+
+        assert!(camera(10.0).is_err());
+    }
+
+    #[test]
+    fn exit_pupil_bounds_everything_that_gets_through() {
+        let camera = camera(1000.0).unwrap();
+        let max_r = camera.film_diagonal / 2.0;
+        let rear = 1.5 * camera.rear_element_radius();
+
+        for i in [0, PUPIL_INTERVALS / 2, PUPIL_INTERVALS - 1] {
+            let bounds = camera.exit_pupil_bounds[i];
+            let r = (i as f64 + 0.5) / PUPIL_INTERVALS as f64 * max_r;
+            for j in 0..=40 {
+                for k in 0..=40 {
+                    let x = rear * (j as f64 / 20.0 - 1.0);
+                    let y = rear * (k as f64 / 20.0 - 1.0);
+                    let p_film = Point::new(r, 0.0, 0.0);
+                    let p_rear = Point::new(x, y, camera.lens_rear_z());
+                    if camera
+                        .trace_lenses_from_film(&Ray::new(p_film, p_rear - p_film))
+                        .is_some()
+                    {
+                        assert!(bounds.inside(x, y));
+                    }
+                }
+            }
+
+            // samples for a film point off the x axis are the same bounds turned round to it
+            let (s, c) = (0.3f64.sin(), 0.3f64.cos());
+            let (p, area) = camera.sample_exit_pupil(c * r, s * r, 0.2, 0.7);
+            assert_eq!(area, bounds.area());
+            assert!(bounds.inside(c * p.x + s * p.y, c * p.y - s * p.x));
+        }
+        assert_eq!(camera.axial_area, camera.exit_pupil_bounds[0].area());
+    }
+}
@@ -1,20 +1,34 @@
 pub mod aabb;
+pub mod animation;
 pub mod bvh;
 pub mod camera;
+pub mod csg;
+pub mod curve;
+pub mod displacement;
+pub mod heightfield;
 pub mod hittable;
 pub mod hittable_list;
 pub mod image;
+pub mod lens;
 pub mod material;
 pub mod mesh;
 pub mod obj;
 pub mod object;
 pub mod onb;
+pub mod patch;
 pub mod pdf;
+pub mod quad;
+pub mod quadric;
 pub mod ray;
 pub mod rect;
+pub mod scatter;
 pub mod scenes;
+pub mod sdf;
 pub mod sphere;
+pub mod stereo;
+pub mod subdivision;
 pub mod texture;
+pub mod torus;
 pub mod utils;
 pub mod vec3;
 
@@ -1,5 +1,7 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex, RwLock};
 use std::thread;
 use std::time::Instant;
@@ -13,10 +15,12 @@ use rust_raytracer::material::Scatterable;
 use rust_raytracer::object::Object;
 use rust_raytracer::pdf::{HittablePDF, MixturePDF, PDF};
 use rust_raytracer::ray::Ray;
-use rust_raytracer::scenes::{new_scene, SceneConfig};
+use rust_raytracer::scenes::{new_scene, new_scene_at, SceneConfig};
+use rust_raytracer::stereo::StereoCamera;
 use rust_raytracer::BIAS;
 
-use rust_raytracer::camera::Camera;
+use rust_raytracer::camera::{Camera, CameraType};
+use rust_raytracer::lens::{load_lens, RealisticCamera};
 
 use rust_raytracer::image::Image;
 
@@ -83,9 +87,55 @@ fn ray_color(
     }
 }
 
-fn raytrace(image_width: usize, scene_config: Arc<SceneConfig>, frame: Arc<RwLock<ColorImage>>) {
+// a window of the frame in pixels, top left inclusive and bottom right exclusive
+#[derive(Clone, Copy)]
+struct Crop {
+    x0: usize,
+    y0: usize,
+    x1: usize,
+    y1: usize,
+}
+
+impl Crop {
+    fn contains(&self, x: usize, y: usize) -> bool {
+        x >= self.x0 && x < self.x1 && y >= self.y0 && y < self.y1
+    }
+
+    fn overlaps(&self, other: &Crop) -> bool {
+        self.x0 < other.x1 && other.x0 < self.x1 && self.y0 < other.y1 && other.y0 < self.y1
+    }
+}
+
+#[derive(Clone, Copy)]
+struct RenderSettings {
+    image_width: usize,
+    samples_per_pixel: u32,
+    focus_point: Option<(f64, f64)>,
+    // only the pixels inside are rendered, the rest of the frame is left as it is. the camera
+    // still covers the whole image
+    crop: Option<Crop>,
+}
+
+impl RenderSettings {
+    fn new(image_width: usize) -> Self {
+        Self {
+            image_width,
+            samples_per_pixel: 100,
+            focus_point: None,
+            crop: None,
+        }
+    }
+}
+
+fn raytrace(
+    scene_config: Arc<SceneConfig>,
+    settings: RenderSettings,
+    frame: Arc<RwLock<ColorImage>>,
+    cancel: Arc<AtomicBool>,
+) {
+    let image_width = settings.image_width;
     let image_height: usize = (image_width as f64 / scene_config.aspect_ratio) as usize;
-    let samples_per_pixel: u32 = 100;
+    let samples_per_pixel = settings.samples_per_pixel;
     const MAX_DEPTH: u32 = 5;
 
     let world = &scene_config.world;
@@ -96,29 +146,119 @@ fn raytrace(image_width: usize, scene_config: Arc<SceneConfig>, frame: Arc<RwLoc
 
     let lights = scene_config.lights.clone();
 
-    let camera = Arc::new(
-        Camera::new(
-            scene_config.lookfrom,
-            scene_config.lookat,
-            scene_config.vup,
-            scene_config.vfov,
-            scene_config.aspect_ratio,
-            scene_config.aperture,
-            scene_config.dist_to_focus,
-        )
-        .with_time(0.0, 1.0),
-    );
+    let mut camera = Camera::new(
+        scene_config.lookfrom,
+        scene_config.lookat,
+        scene_config.vup,
+        scene_config.vfov,
+        match scene_config.stereo {
+            Some(stereo) => stereo.layout.eye_aspect(scene_config.aspect_ratio),
+            None => scene_config.aspect_ratio,
+        },
+        scene_config.aperture,
+        scene_config.dist_to_focus,
+    )
+    .with_time(0.0, 1.0)
+    .with_shutter(scene_config.shutter);
+
+    // a click in a stereo image focuses on the same spot as seen through the centre camera
+    let focus_point = match scene_config.stereo {
+        Some(stereo) => settings.focus_point.map(|(s, t)| {
+            let (_, s, t) = stereo.layout.split(s, t);
+            (s, t)
+        }),
+        None => settings.focus_point,
+    };
+    let focus_point = if scene_config.autofocus {
+        focus_point.or(Some((0.5, 0.5)))
+    } else {
+        focus_point
+    };
+    let mut focus_dist = scene_config.dist_to_focus;
+    if let Some((s, t)) = focus_point {
+        if let Some(d) = camera.focus_distance_at(world, s, t) {
+            focus_dist = d;
+            camera = camera.with_focus_dist(focus_dist);
+        }
+    }
+
+    let camera = Arc::new(match (&scene_config.lens_file, scene_config.stereo) {
+        (_, Some(stereo)) => CameraType::Stereo(Box::new(
+            StereoCamera::new(
+                scene_config.lookfrom,
+                scene_config.lookat,
+                scene_config.vup,
+                scene_config.vfov,
+                scene_config.aspect_ratio,
+                scene_config.aperture,
+                focus_dist,
+                stereo,
+            )
+            .with_time(0.0, 1.0)
+            .with_shutter(scene_config.shutter),
+        )),
+        (None, None) => CameraType::Thin(camera),
+        (Some(path), None) => {
+            let realistic = load_lens(path.clone()).and_then(|elements| {
+                let focused = |focus_dist| {
+                    RealisticCamera::new(
+                        scene_config.lookfrom,
+                        scene_config.lookat,
+                        scene_config.vup,
+                        elements.clone(),
+                        scene_config.lens_scale,
+                        scene_config.film_diagonal,
+                        scene_config.aspect_ratio,
+                        focus_dist,
+                    )
+                };
+                // something clicked on too close for the lens keeps the scene's own focus
+                focused(focus_dist).or_else(|e| {
+                    println!("error: {}", e);
+                    focused(scene_config.dist_to_focus)
+                })
+            });
+            match realistic {
+                Ok(realistic) => CameraType::Realistic(
+                    realistic
+                        .with_time(0.0, 1.0)
+                        .with_shutter(scene_config.shutter),
+                ),
+                // without a working lens the scene still renders through the thin lens
+                Err(e) => {
+                    println!("error: {}", e);
+                    CameraType::Thin(camera)
+                }
+            }
+        }
+    });
 
     let pool = ThreadPoolBuilder::new().num_threads(12).build().unwrap();
 
     let mut chunks: Vec<(usize, usize)> = vec![];
     let chunk_size = 16;
-    let chunk_width = image_height / chunk_size;
-    let chunk_height = image_height / chunk_size;
+    let chunk_width = image_height.div_ceil(chunk_size);
+    let chunk_height = image_width.div_ceil(chunk_size);
+
+    // whether the pixel at column x, y rows up from the bottom gets rendered
+    let crop = settings.crop;
+    let in_crop = move |x: usize, y: usize| {
+        x < image_width
+            && y < image_height
+            && crop.is_none_or(|c| c.contains(x, image_height - y - 1))
+    };
 
     for j in 0..chunk_height {
         for i in 0..chunk_width {
-            chunks.push((i, j))
+            let tile = Crop {
+                x0: chunk_size * j,
+                y0: image_height.saturating_sub(chunk_size * (i + 1)),
+                x1: chunk_size * (j + 1),
+                y1: image_height - chunk_size * i,
+            };
+            if crop.is_none_or(|c| c.overlaps(&tile)) {
+                chunks.push((i, j))
+            }
         }
     }
 
@@ -130,7 +270,11 @@ fn raytrace(image_width: usize, scene_config: Arc<SceneConfig>, frame: Arc<RwLoc
             let camera = camera.clone();
             let image = arc_image.clone();
             let arc_lights = lights.clone();
+            let cancel = cancel.clone();
             s.spawn(move |_| {
+                if cancel.load(Ordering::Relaxed) {
+                    return;
+                }
                 let mut colors =
                     vec![Color::new(samples_per_pixel as f64, 0.0, 0.0); chunk_size * chunk_size];
                 let mut img = image.lock().unwrap();
@@ -138,7 +282,7 @@ fn raytrace(image_width: usize, scene_config: Arc<SceneConfig>, frame: Arc<RwLoc
                     let pos_y = chunk_size * chunk.0 + (i % chunk_size);
                     let pos_x = chunk_size * chunk.1 + (i / chunk_size);
 
-                    if pos_y > image_height || pos_x > image_width {
+                    if !in_crop(pos_x, pos_y) {
                         continue;
                     }
 
@@ -146,13 +290,13 @@ fn raytrace(image_width: usize, scene_config: Arc<SceneConfig>, frame: Arc<RwLoc
                 }
                 drop(img);
                 for j in 0..chunk_size {
-                    let y = chunk_size * chunk.1 + j;
-                    if y > image_height {
-                        continue;
+                    if cancel.load(Ordering::Relaxed) {
+                        return;
                     }
+                    let y = chunk_size * chunk.1 + j;
                     for i in 0..chunk_size {
                         let x = chunk_size * chunk.0 + i;
-                        if x > image_width {
+                        if !in_crop(y, x) {
                             continue;
                         }
                         let mut pixel_color = Color::default();
@@ -161,14 +305,16 @@ fn raytrace(image_width: usize, scene_config: Arc<SceneConfig>, frame: Arc<RwLoc
                                 ((y as f64) + random_double_normal()) / (image_width - 1) as f64;
                             let v =
                                 ((x as f64) + random_double_normal()) / (image_height - 1) as f64;
-                            let r = camera.get_ray(u, v);
-                            pixel_color += ray_color(
-                                &r,
-                                &arc_world,
-                                arc_lights.clone(),
-                                &background,
-                                MAX_DEPTH,
-                            );
+                            if let Some((r, weight)) = camera.generate_ray(u, v) {
+                                pixel_color += weight
+                                    * ray_color(
+                                        &r,
+                                        &arc_world,
+                                        arc_lights.clone(),
+                                        &background,
+                                        MAX_DEPTH,
+                                    );
+                            }
                         }
                         colors[j * chunk_size + i] = pixel_color;
                     }
@@ -178,7 +324,7 @@ fn raytrace(image_width: usize, scene_config: Arc<SceneConfig>, frame: Arc<RwLoc
                     let pos_y = chunk_size * chunk.0 + (i % chunk_size);
                     let pos_x = chunk_size * chunk.1 + (i / chunk_size);
 
-                    if pos_y > image_height || pos_x > image_width {
+                    if !in_crop(pos_x, pos_y) {
                         continue;
                     }
 
@@ -191,7 +337,71 @@ fn raytrace(image_width: usize, scene_config: Arc<SceneConfig>, frame: Arc<RwLoc
     println!("Time elapsed in ray_trace() is: {:?}", duration);
 }
 
+// frames <scene> <first> <last> <output dir> [fps]
+fn render_frames(args: &[String]) {
+    let (scene, first, last, fps) = match parse_frame_args(args) {
+        Some(parsed) => parsed,
+        None => {
+            println!("usage: rust-raytracer frames <scene> <first> <last> <output dir> [fps]");
+            return;
+        }
+    };
+    let out_dir = Path::new(&args[3]);
+
+    if let Err(e) = std::fs::create_dir_all(out_dir) {
+        println!("error: {}", e);
+        return;
+    }
+
+    let width: usize = 700;
+    for n in first..=last {
+        let scene_config = Arc::new(new_scene_at(scene, n as f64 / fps));
+        let height = (width as f64 / scene_config.aspect_ratio) as usize;
+        let frame = Arc::new(RwLock::new(ColorImage::new(
+            [width, height],
+            Color32::BLACK,
+        )));
+
+        raytrace(
+            scene_config,
+            RenderSettings::new(width),
+            frame.clone(),
+            Arc::new(AtomicBool::new(false)),
+        );
+
+        let path = out_dir.join(format!("frame_{:04}.png", n));
+        let image = frame.read().unwrap().clone();
+        match save_png(path.display().to_string(), &image) {
+            Err(e) => println!("error: {}", e),
+            Ok(_) => println!("wrote {}", path.display()),
+        }
+    }
+}
+
+// scene, first and last frame, and frames per second, or none if any of them don't parse
+fn parse_frame_args(args: &[String]) -> Option<(u32, u32, u32, f64)> {
+    if args.len() < 4 {
+        return None;
+    }
+    let fps = match args.get(4) {
+        Some(fps) => fps.parse().ok()?,
+        None => 24.0,
+    };
+    Some((
+        args[0].parse().ok()?,
+        args[1].parse().ok()?,
+        args[2].parse().ok()?,
+        fps,
+    ))
+}
+
 fn main() -> Result<(), Error> {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() > 1 && args[1] == "frames" {
+        render_frames(&args[2..]);
+        return Ok(());
+    }
+
     let width: usize = 700;
     let scene_cfg = new_scene(7);
     let height = (width as f64 / scene_cfg.aspect_ratio) as usize;
@@ -212,6 +422,14 @@ fn main() -> Result<(), Error> {
 struct MyEguiApp {
     texture: Option<egui::TextureHandle>,
     frame_thing: Arc<RwLock<ColorImage>>,
+    image_width: usize,
+    scene_config: Arc<SceneConfig>,
+    cancel: Arc<AtomicBool>,
+    focus_point: Option<(f64, f64)>,
+    // samples per pixel for a render region
+    region_samples: u32,
+    // screen positions the region selection was dragged between
+    selection: Option<(egui::Pos2, egui::Pos2)>,
 }
 
 impl MyEguiApp {
@@ -229,26 +447,88 @@ impl MyEguiApp {
             [image_width, height],
             Color32::BLACK,
         )));
-        {
-            let frame_thing = frame_thing.clone();
-            let scene_config = scene_config.clone();
-            thread::spawn(move || {
-                raytrace(image_width, scene_config, frame_thing);
-            });
-        }
-        Self {
-            frame_thing: frame_thing.clone(),
+        let mut app = Self {
+            frame_thing,
+            image_width,
+            scene_config,
+            region_samples: 1000,
             ..Default::default()
+        };
+        app.render(None);
+        app
+    }
+
+    // stops any render in flight and starts a new one. a crop only re-renders that region,
+    // at region_samples, over what is already in the frame
+    fn render(&mut self, crop: Option<Crop>) {
+        self.cancel.store(true, Ordering::Relaxed);
+        self.cancel = Arc::new(AtomicBool::new(false));
+
+        let mut settings = RenderSettings::new(self.image_width);
+        settings.focus_point = self.focus_point;
+        if crop.is_some() {
+            settings.crop = crop;
+            settings.samples_per_pixel = self.region_samples;
         }
+
+        let frame_thing = self.frame_thing.clone();
+        let scene_config = self.scene_config.clone();
+        let cancel = self.cancel.clone();
+        thread::spawn(move || {
+            raytrace(scene_config, settings, frame_thing, cancel);
+        });
+    }
+}
+
+// the pixels of an image of `size` covered by the screen rectangle between a and b, when the
+// image is drawn in `rect`
+fn crop_from_selection(
+    rect: egui::Rect,
+    a: egui::Pos2,
+    b: egui::Pos2,
+    size: [usize; 2],
+) -> Option<Crop> {
+    let to_pixel = |p: egui::Pos2| {
+        let p = rect.clamp(p) - rect.min;
+        (
+            (p.x / rect.width() * size[0] as f32).round() as usize,
+            (p.y / rect.height() * size[1] as f32).round() as usize,
+        )
+    };
+    let (ax, ay) = to_pixel(a);
+    let (bx, by) = to_pixel(b);
+
+    let crop = Crop {
+        x0: ax.min(bx),
+        y0: ay.min(by),
+        x1: ax.max(bx),
+        y1: ay.max(by),
+    };
+    if crop.x0 == crop.x1 || crop.y0 == crop.y1 {
+        return None;
     }
+    Some(crop)
 }
 
 fn image_as_u8(image: &ColorImage) -> Vec<u8> {
     image.pixels.iter().flat_map(Color32::to_array).collect()
 }
 
+fn save_png(path: String, image: &ColorImage) -> image::ImageResult<()> {
+    image::save_buffer_with_format(
+        path,
+        &image_as_u8(image),
+        image.width() as u32,
+        image.height() as u32,
+        image::ColorType::Rgba8,
+        image::ImageFormat::Png,
+    )
+}
+
 impl eframe::App for MyEguiApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        let mut focus_point = None;
+        let mut crop = None;
         egui::CentralPanel::default().show(ctx, |ui| {
             let frame_thing = self.frame_thing.read().unwrap();
             let current_image = frame_thing.clone();
@@ -258,31 +538,73 @@ impl eframe::App for MyEguiApp {
                 Default::default(),
             ));
 
-            if ui.button("save image").clicked() {
-                if let Some(path) = rfd::FileDialog::new().save_file() {
-                    let res = image::save_buffer_with_format(
-                        path.display().to_string(),
-                        &image_as_u8(&current_image),
-                        current_image.width() as u32,
-                        current_image.height() as u32,
-                        image::ColorType::Rgba8,
-                        image::ImageFormat::Png,
-                    );
+            ui.horizontal(|ui| {
+                if ui.button("save image").clicked() {
+                    if let Some(path) = rfd::FileDialog::new().save_file() {
+                        let res = save_png(path.display().to_string(), &current_image);
 
-                    match res {
-                        Err(e) => println!("error: {}", e.to_string()),
-                        Ok(_) => {}
-                    }
+                        match res {
+                            Err(e) => println!("error: {}", e.to_string()),
+                            Ok(_) => {}
+                        }
+                    };
                 };
-            };
+
+                ui.label("region samples");
+                ui.add(egui::DragValue::new(&mut self.region_samples).clamp_range(1..=100000));
+            });
 
             if let Some(texture) = self.texture.as_ref() {
-                ui.image(texture, ui.available_size())
+                let response = ui.add(
+                    egui::Image::new(texture, ui.available_size())
+                        .sense(egui::Sense::click_and_drag()),
+                );
+
+                // click to focus
+                if response.clicked() {
+                    if let Some(pos) = response.interact_pointer_pos() {
+                        let rect = response.rect;
+                        let s = ((pos.x - rect.min.x) / rect.width()) as f64;
+                        let t = 1.0 - ((pos.y - rect.min.y) / rect.height()) as f64;
+                        focus_point = Some((s, t));
+                    }
+                }
+
+                // drag to re-render a region
+                if response.drag_started() {
+                    self.selection = response.interact_pointer_pos().map(|pos| (pos, pos));
+                }
+                if response.dragged() {
+                    if let (Some((start, _)), Some(pos)) =
+                        (self.selection, response.interact_pointer_pos())
+                    {
+                        self.selection = Some((start, pos));
+                    }
+                }
+                if let Some((a, b)) = self.selection {
+                    ui.painter().rect_stroke(
+                        egui::Rect::from_two_pos(a, b),
+                        0.0,
+                        egui::Stroke::new(1.0, Color32::WHITE),
+                    );
+                }
+                if response.drag_released() {
+                    if let Some((a, b)) = self.selection.take() {
+                        crop = crop_from_selection(response.rect, a, b, current_image.size);
+                    }
+                }
             } else {
-                ui.spinner()
+                ui.spinner();
             };
 
             ctx.request_repaint()
         });
+
+        if focus_point.is_some() {
+            self.focus_point = focus_point;
+            self.render(None);
+        } else if crop.is_some() {
+            self.render(crop);
+        }
     }
 }
@@ -15,6 +15,17 @@ pub enum Material {
     Metal(Metal),
     Dielectric(Dielectric),
     DiffuseLight(DiffuseLight),
+    Bumped(Bumped),
+}
+
+impl Material {
+    // the material underneath any bump or normal maps
+    pub fn base(&self) -> &Material {
+        match self {
+            Material::Bumped(b) => b.base.base(),
+            _ => self,
+        }
+    }
 }
 
 impl Scatterable for Material {
@@ -24,6 +35,7 @@ impl Scatterable for Material {
             Material::Metal(m) => m.scatter(ray_in, hit_record),
             Material::Dielectric(d) => d.scatter(ray_in, hit_record),
             Material::DiffuseLight(dl) => dl.scatter(ray_in, hit_record),
+            Material::Bumped(b) => b.scatter(ray_in, hit_record),
         }
     }
 
@@ -33,6 +45,7 @@ impl Scatterable for Material {
             Material::DiffuseLight(dl) => dl.emitted(ray_in, hit_record, u, v, p),
             Material::Metal(m) => m.emitted(ray_in, hit_record, u, v, p),
             Material::Dielectric(d) => d.emitted(ray_in, hit_record, u, v, p),
+            Material::Bumped(b) => b.emitted(ray_in, hit_record, u, v, p),
         }
     }
 
@@ -42,6 +55,7 @@ impl Scatterable for Material {
             Material::DiffuseLight(dl) => dl.scatter_pdf(ray_in, hit_record, scattered),
             Material::Metal(m) => m.scatter_pdf(ray_in, hit_record, scattered),
             Material::Dielectric(d) => d.scatter_pdf(ray_in, hit_record, scattered),
+            Material::Bumped(b) => b.scatter_pdf(ray_in, hit_record, scattered),
         }
     }
 }
@@ -96,9 +110,7 @@ impl Scatterable for Lambertain {
     fn scatter(&self, _ray_in: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
         let scatter_record = ScatterRecord {
             specular_ray: None,
-            attenuation: self
-                .albedo
-                .value(hit_record.uv.0, hit_record.uv.1, &hit_record.p),
+            attenuation: self.albedo.value_at(hit_record),
             pdf_ptr: Some(Arc::new(CosinePDF::new(&hit_record.normal))),
         };
         Some(scatter_record)
@@ -218,3 +230,151 @@ impl Scatterable for DiffuseLight {
         None
     }
 }
+
+// where a Bumped material gets its shading normal from
+pub enum Perturbation {
+    // tangent space normal map, red along dpdu, green along dpdv and blue out of the surface
+    NormalMap(Arc<Texture>),
+    // height from the average of the texture's channels, times the scale
+    Bump(Arc<Texture>, f64),
+}
+
+// another material with its shading normal moved by a normal or bump map. only works on
+// surfaces that fill in dpdu and dpdv, anything else is left as it is
+pub struct Bumped {
+    base: Arc<Material>,
+    perturbation: Perturbation,
+}
+
+impl Bumped {
+    pub fn normal_map(base: Arc<Material>, map: Arc<Texture>) -> Self {
+        Self {
+            base,
+            perturbation: Perturbation::NormalMap(map),
+        }
+    }
+
+    pub fn bump(base: Arc<Material>, height: Arc<Texture>, scale: f64) -> Self {
+        Self {
+            base,
+            perturbation: Perturbation::Bump(height, scale),
+        }
+    }
+
+    fn perturbed_normal(&self, rec: &HitRecord) -> Option<Vec3> {
+        let n = rec.normal;
+        if rec.dpdu.length_squared() == 0.0 || rec.dpdv.length_squared() == 0.0 {
+            return None;
+        }
+
+        match &self.perturbation {
+            Perturbation::NormalMap(map) => {
+                // tangent frame around the normal, with the bitangent on the dpdv side
+                let tangent = rec.dpdu - n.dot(rec.dpdu) * n;
+                if tangent.length_squared() == 0.0 {
+                    return None;
+                }
+                let tangent = tangent.unit();
+                let mut bitangent = n.cross(tangent);
+                if bitangent.dot(rec.dpdv) < 0.0 {
+                    bitangent = -bitangent;
+                }
+
+                let c = 2.0 * map.value(rec.uv.0, rec.uv.1, &rec.p) - Color::new(1.0, 1.0, 1.0);
+                Some((c.x * tangent + c.y * bitangent + c.z * n).unit())
+            }
+            Perturbation::Bump(height, scale) => {
+                // finite differences of the height a small step along u and v
+                let du = 0.0005;
+                let h = |u: f64, v: f64, p: &Point| {
+                    let c = height.value(u, v, p);
+                    scale * (c.x + c.y + c.z) / 3.0
+                };
+                let (u, v) = rec.uv;
+                let here = h(u, v, &rec.p);
+                let dhdu = (h(u + du, v, &(rec.p + du * rec.dpdu)) - here) / du;
+                let dhdv = (h(u, v + du, &(rec.p + du * rec.dpdv)) - here) / du;
+
+                let dpdu = rec.dpdu + dhdu * n;
+                let dpdv = rec.dpdv + dhdv * n;
+                let bumped = dpdu.cross(dpdv);
+                if bumped.length_squared() == 0.0 {
+                    return None;
+                }
+                // keep it on the same side as the normal it replaces
+                let bumped = bumped.unit();
+                Some(if bumped.dot(n) < 0.0 { -bumped } else { bumped })
+            }
+        }
+    }
+
+    // the hit record with the moved normal, unless that would face away from the ray
+    fn shade(&self, ray_in: &Ray, rec: &HitRecord) -> HitRecord {
+        let mut shaded = rec.clone();
+        if let Some(n) = self.perturbed_normal(rec) {
+            if n.dot(ray_in.dir) < 0.0 {
+                shaded.normal = n;
+            }
+        }
+        shaded
+    }
+}
+
+impl Scatterable for Bumped {
+    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
+        self.base.scatter(ray_in, &self.shade(ray_in, hit_record))
+    }
+
+    fn scatter_pdf(&self, ray_in: &Ray, hit_record: &HitRecord, scattered: &Ray) -> Option<f64> {
+        self.base
+            .scatter_pdf(ray_in, &self.shade(ray_in, hit_record), scattered)
+    }
+
+    fn emitted(&self, ray_in: &Ray, hit_record: &HitRecord, u: f64, v: f64, p: &Point) -> Color {
+        self.base
+            .emitted(ray_in, &self.shade(ray_in, hit_record), u, v, p)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{Bumped, Lambertain, Material};
+    use crate::{
+        hittable::Hittable,
+        quad::Quad,
+        ray::Ray,
+        texture::{SolidColor, Texture},
+        vec3::{Point, Vec3},
+    };
+
+    #[test]
+    fn normal_map_tilts_along_tangent() {
+        let base = Arc::new(Material::Lambertain(Lambertain::default()));
+        let map = |r: f64, g: f64, b: f64| Arc::new(Texture::SolidColor(SolidColor::new(r, g, b)));
+        // facing back down the ray, with u along +x and v along -y
+        let quad = Quad::new(
+            Point::new(-1.0, 1.0, 0.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(0.0, -2.0, 0.0),
+            base.clone(),
+        );
+        let r = Ray::new(Point::new(0.0, 0.0, -1.0), Vec3::new(0.0, 0.0, 1.0));
+        let rec = quad.hit(&r, 0.001, f64::MAX).unwrap();
+
+        let flat = Bumped::normal_map(base.clone(), map(0.5, 0.5, 1.0));
+        let n = flat.shade(&r, &rec).normal;
+        assert!((n - rec.normal).length() < 1e-9);
+
+        // half way from the normal towards dpdu, then towards dpdv
+        let s = 0.5 * 0.5_f64.sqrt();
+        let tilted = Bumped::normal_map(base.clone(), map(0.5 + s, 0.5, 0.5 + s));
+        let n = tilted.shade(&r, &rec).normal;
+        assert!((n - Vec3::new(1.0, 0.0, -1.0).unit()).length() < 1e-9);
+
+        let tilted = Bumped::normal_map(base, map(0.5, 0.5 + s, 0.5 + s));
+        let n = tilted.shade(&r, &rec).normal;
+        assert!((n - Vec3::new(0.0, -1.0, -1.0).unit()).length() < 1e-9);
+    }
+}
@@ -1,166 +1,790 @@
-use core::panic;
-use std::sync::Arc;
+use std::{collections::HashMap, hash::Hash, sync::Arc};
 
-use glam::{DVec2, Vec2};
+use glam::DVec2;
 
 use crate::{
     aabb::AABB,
-    bvh::BVHNode,
+    displacement::Displacement,
     hittable::{HitRecord, Hittable},
-    hittable_list::HittableList,
     material::Material,
-    obj::load_obj,
-    object::Object,
+    obj::{load_obj, OBJ},
     ray::Ray,
+    subdivision::PolyMesh,
     utils::random_double_normal,
-    vec3::{Point, Vec3},
+    vec3::{Color, Point, Vec3},
 };
 
-pub struct Triangle {
-    pub vs: (Vec3, Vec3, Vec3),
-    pub normals: Option<(Vec3, Vec3, Vec3)>,
-    pub uvs: Option<(DVec2, DVec2, DVec2)>,
+// vertex attributes, kept as f64 or packed down to f32 to halve the memory of big meshes
+enum Store {
+    Double(Vec<f64>),
+    Single(Vec<f32>),
 }
 
-impl Hittable for Triangle {
-    fn bounding_box(&self, _time: (f64, f64)) -> Option<AABB> {
-        let min = Vec3::new(
-            self.vs.0.x.min(self.vs.1.x).min(self.vs.2.x),
-            self.vs.0.y.min(self.vs.1.y).min(self.vs.2.y),
-            self.vs.0.z.min(self.vs.1.z).min(self.vs.2.z),
-        );
-        let max = Vec3::new(
-            self.vs.0.x.max(self.vs.1.x).max(self.vs.2.x),
-            self.vs.0.y.max(self.vs.1.y).max(self.vs.2.y),
-            self.vs.0.z.max(self.vs.1.z).max(self.vs.2.z),
-        );
-        Some(AABB { min, max })
+impl Store {
+    fn new(values: Vec<f64>, single: bool) -> Self {
+        if single {
+            Store::Single(values.iter().map(|v| *v as f32).collect())
+        } else {
+            Store::Double(values)
+        }
     }
 
-    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
-        match triangle_intersect(r, self.vs) {
-            Some((t, mut uv, mut normal)) => {
-                if t < t_max && t > t_min {
-                    let mut new_rec = HitRecord::default();
-
-                    if let Some(normals) = self.normals {
-                        normal =
-                            (1.0 - uv.0 - uv.1) * normals.0 + uv.0 * normals.1 + uv.1 * normals.2
-                    }
-
-                    if let Some(uvs) = self.uvs {
-                        let v = (1.0 - uv.0 - uv.1) * uvs.0 + uv.0 * uvs.1 + uv.1 * uvs.2;
-                        uv.0 = v.x;
-                        uv.1 = v.y;
-                    }
+    fn len(&self) -> usize {
+        match self {
+            Store::Double(values) => values.len(),
+            Store::Single(values) => values.len(),
+        }
+    }
 
-                    new_rec.p = r.at(t);
-                    new_rec.set_face_normal(r, normal);
-                    new_rec.t = t;
-                    new_rec.uv = uv;
-                    Some(new_rec)
-                } else {
-                    None
-                }
-            }
-            _ => None,
+    fn get(&self, i: usize) -> f64 {
+        match self {
+            Store::Double(values) => values[i],
+            Store::Single(values) => values[i] as f64,
         }
     }
 
-    fn pdf_value(&self, o: &Point, v: &Point) -> f64 {
-        match self.hit(&Ray::new(*o, *v), 0.001, f64::MAX) {
-            None => 0.0,
-            Some(rec) => {
-                let area = 0.5
-                    * (self.vs.1 - self.vs.0)
-                        .cross(self.vs.2 - self.vs.0)
-                        .length();
-                let dist_sqrd = rec.t * rec.t * v.length_squared();
-                let cos = (v.dot(rec.normal)).abs() / v.length();
+    fn vec3(&self, i: u32) -> Vec3 {
+        let i = 3 * i as usize;
+        Vec3::new(self.get(i), self.get(i + 1), self.get(i + 2))
+    }
 
-                dist_sqrd / (cos * area)
-            }
-        }
+    fn vec2(&self, i: u32) -> DVec2 {
+        let i = 2 * i as usize;
+        DVec2::new(self.get(i), self.get(i + 1))
     }
 
-    fn random(&self, o: &Vec3) -> Vec3 {
-        let r1 = random_double_normal();
-        let r2 = random_double_normal();
+    // tangents are four values, see vertex_tangents
+    fn tangent(&self, i: u32) -> (Vec3, f64) {
+        let i = 4 * i as usize;
+        (
+            Vec3::new(self.get(i), self.get(i + 1), self.get(i + 2)),
+            self.get(i + 3),
+        )
+    }
+}
 
-        let sqrt_r = r1.sqrt();
-        let a = 1.0 - sqrt_r;
-        let b = sqrt_r * (1.0 - r2);
-        let c = sqrt_r * r2;
+// a triangle corner, before corners with the same key are merged into one vertex
+struct Corner<K> {
+    key: K,
+    p: Point,
+    normal: Option<Vec3>,
+    uv: Option<DVec2>,
+    color: Option<Color>,
+}
+
+// the corners of a triangle and its material
+type Face<K> = ([Corner<K>; 3], u32);
+
+// vertex buffers being filled in, with one vertex for each different combination of position,
+// normal and uv. vertices missing a normal, uv or colour get zeros
+#[derive(Default)]
+struct Buffers {
+    positions: Vec<f64>,
+    normals: Vec<f64>,
+    uvs: Vec<f64>,
+    colors: Vec<f64>,
+    has_normals: bool,
+    has_uvs: bool,
+    has_colors: bool,
+    indices: Vec<[u32; 3]>,
+    // one per triangle, empty when they're all 0
+    materials: Vec<u32>,
+    // the second keyframe of a deforming mesh, empty otherwise
+    end_positions: Vec<f64>,
+    end_normals: Vec<f64>,
+}
 
-        let x = a * self.vs.0.x + b * self.vs.1.x + c * self.vs.2.x;
-        let y = a * self.vs.0.y + b * self.vs.1.y + c * self.vs.2.y;
-        let z = a * self.vs.0.z + b * self.vs.1.z + c * self.vs.2.z;
+impl Buffers {
+    // merges corners with the same key. corners of the end keyframe go to the same vertex as
+    // the matching corner of the start, so both have the same vertices
+    fn weld<K: Hash + Eq>(start: Vec<Face<K>>, end: Option<Vec<Face<K>>>) -> Self {
+        if let Some(end) = &end {
+            assert_eq!(start.len(), end.len(), "keyframes have different faces");
+        }
+
+        let mut buffers = Buffers::default();
+        let mut welded = HashMap::new();
+        for (f, (corners, material)) in start.into_iter().enumerate() {
+            let mut tri = [0; 3];
+            for (c, corner) in corners.into_iter().enumerate() {
+                let end = end.as_ref().map(|end| &end[f].0[c]);
+                tri[c] = *welded.entry(corner.key).or_insert_with(|| {
+                    buffers.push(corner.p, corner.normal, corner.uv, corner.color, end)
+                });
+            }
+            buffers.indices.push(tri);
+            buffers.materials.push(material);
+        }
+        if buffers.materials.iter().all(|m| *m == 0) {
+            buffers.materials.clear();
+        }
+        buffers
+    }
 
-        let v = Vec3::new(x, y, z);
-        v - o
+    fn push<K>(
+        &mut self,
+        p: Point,
+        normal: Option<Vec3>,
+        uv: Option<DVec2>,
+        color: Option<Color>,
+        end: Option<&Corner<K>>,
+    ) -> u32 {
+        let n = normal.unwrap_or_default();
+        self.has_normals |= normal.is_some();
+        self.has_uvs |= uv.is_some();
+        self.has_colors |= color.is_some();
+        let uv = uv.unwrap_or_default();
+        let c = color.unwrap_or_default();
+        self.positions.extend([p.x, p.y, p.z]);
+        self.normals.extend([n.x, n.y, n.z]);
+        self.uvs.extend([uv.x, uv.y]);
+        self.colors.extend([c.x, c.y, c.z]);
+
+        if let Some(end) = end {
+            let n = end.normal.unwrap_or_default();
+            self.end_positions.extend([end.p.x, end.p.y, end.p.z]);
+            self.end_normals.extend([n.x, n.y, n.z]);
+        }
+        (self.positions.len() / 3 - 1) as u32
     }
 }
 
+// flattened bvh over the triangles. an interior node's first child comes straight after it and
+// the second is at `offset`. a leaf holds the `count` triangles starting at `offset`
+struct Node {
+    bx: AABB,
+    offset: u32,
+    count: u32,
+    axis: u8,
+}
+
+const LEAF_SIZE: usize = 4;
+
 pub struct Mesh {
-    // pub verts: HittableList,
-    pub node: BVHNode,
+    positions: Store,
+    normals: Option<Store>,
+    uvs: Option<Store>,
+    // made from the uvs, so normal and bump maps line up with the ones baked for the mesh
+    tangents: Option<Store>,
+    colors: Option<Store>,
+    // where the vertices have moved to by the end of `time`, for meshes that deform
+    end_positions: Option<Store>,
+    end_normals: Option<Store>,
+    time: (f64, f64),
+    // three vertices per triangle, counter clockwise, in the order the leaves refer to them
+    indices: Vec<[u32; 3]>,
+    // index into a TriangleMesh's table for each triangle, empty when they all use the first
+    materials: Vec<u32>,
+    // the usemtl names ids 1 on stand for, 0 being faces without one
+    material_names: Vec<String>,
+    nodes: Vec<Node>,
+    // running total of the triangle areas, for picking a point on the mesh
+    areas: Vec<f64>,
     pub bx: Option<AABB>,
 }
 
+#[derive(Clone, Default)]
+pub struct MeshOptions {
+    // levels of loop (all triangles) or catmull-clark (anything else) subdivision
+    pub subdivisions: u32,
+    // edges between faces meeting at more than this many degrees stay sharp when subdividing.
+    // open edges always do
+    pub crease_angle: Option<f64>,
+    // applied after subdividing, the mesh gets its own smooth normals afterwards
+    pub displacement: Option<Displacement>,
+    // store vertices as f32 rather than f64
+    pub single_precision: bool,
+    // gives meshes without normals of their own smooth ones, split where faces meet at more
+    // than this many degrees. none keeps them flat shaded
+    pub smooth_normals: Option<f64>,
+}
+
 impl Mesh {
     pub fn new(path: String) -> Self {
+        Self::with_options(path, MeshOptions::default())
+    }
+
+    pub fn with_options(path: String, options: MeshOptions) -> Self {
         let obj = load_obj(path).expect("invalid obj");
+        let buffers = mesh_buffers(&obj, None, &options);
+        let mut mesh = Self::from_buffers(buffers, (0.0, 0.0), options.single_precision);
+        mesh.material_names = obj.materials;
+        mesh
+    }
 
-        let mut tris = HittableList::new();
+    // every vertex moves in a straight line from where it is in `start` to where it is in `end`
+    // over `time`, for motion blur on things that bend. both files need the same faces in the
+    // same order
+    pub fn from_keyframes(
+        start: String,
+        end: String,
+        time: (f64, f64),
+        options: MeshOptions,
+    ) -> Self {
+        let start = load_obj(start).expect("invalid obj");
+        let end = load_obj(end).expect("invalid obj");
+        let buffers = mesh_buffers(&start, Some(&end), &options);
+        let mut mesh = Self::from_buffers(buffers, time, options.single_precision);
+        mesh.material_names = start.materials;
+        mesh
+    }
 
-        for face in &obj.faces {
-            if face.verts.len() != 3 {
-                panic!("face info doesn't have 3 verts")
+    fn from_buffers(buffers: Buffers, time: (f64, f64), single: bool) -> Self {
+        let positions = Store::new(buffers.positions, single);
+        let end_positions =
+            (!buffers.end_positions.is_empty()).then(|| Store::new(buffers.end_positions, single));
+
+        // boxes around both keyframes hold the triangle all the way between them
+        let mut items: Vec<_> = buffers
+            .indices
+            .iter()
+            .enumerate()
+            .map(|(f, tri)| {
+                let mut points = tri.map(|i| positions.vec3(i)).to_vec();
+                if let Some(end) = &end_positions {
+                    points.extend(tri.map(|i| end.vec3(i)));
+                }
+                let centre = points.iter().fold(Vec3::default(), |sum, p| sum + *p);
+                (f, points_box(&points), centre / points.len() as f64)
+            })
+            .collect();
+
+        let mut nodes = vec![];
+        if !items.is_empty() {
+            build_nodes(&mut items, 0, &mut nodes);
+        }
+        let indices: Vec<_> = items.iter().map(|(f, _, _)| buffers.indices[*f]).collect();
+        let materials = if buffers.materials.is_empty() {
+            vec![]
+        } else {
+            items
+                .iter()
+                .map(|(f, _, _)| buffers.materials[*f])
+                .collect()
+        };
+
+        let normals = buffers
+            .has_normals
+            .then(|| Store::new(buffers.normals, single));
+        let uvs = buffers.has_uvs.then(|| Store::new(buffers.uvs, single));
+        let colors = buffers
+            .has_colors
+            .then(|| Store::new(buffers.colors, single));
+        let tangents = uvs.as_ref().map(|uvs| {
+            let values = vertex_tangents(&positions, normals.as_ref(), uvs, &indices);
+            Store::new(values, single)
+        });
+        let end_normals = (buffers.has_normals && end_positions.is_some())
+            .then(|| Store::new(buffers.end_normals, single));
+
+        let mut mesh = Self {
+            bx: nodes.first().map(|n| n.bx),
+            positions,
+            normals,
+            uvs,
+            tangents,
+            colors,
+            end_positions,
+            end_normals,
+            time,
+            indices,
+            materials,
+            material_names: vec![],
+            nodes,
+            areas: vec![],
+        };
+
+        // areas half way through the motion, where points are picked for light sampling
+        let mut total = 0.0;
+        mesh.areas = (0..mesh.indices.len())
+            .map(|tri| {
+                let (a, b, c) = mesh.vertices(tri, 0.5);
+                total += 0.5 * (b - a).cross(c - a).length();
+                total
+            })
+            .collect();
+        mesh
+    }
+
+    // how far through the motion a ray at `time` is, from 0 to 1
+    fn fraction(&self, time: f64) -> f64 {
+        if self.time.1 > self.time.0 {
+            ((time - self.time.0) / (self.time.1 - self.time.0)).clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+
+    fn mid_time(&self) -> f64 {
+        0.5 * (self.time.0 + self.time.1)
+    }
+
+    fn position(&self, i: u32, s: f64) -> Point {
+        let p = self.positions.vec3(i);
+        match &self.end_positions {
+            Some(end) => p + s * (end.vec3(i) - p),
+            None => p,
+        }
+    }
+
+    fn normal(&self, normals: &Store, i: u32, s: f64) -> Vec3 {
+        let n = normals.vec3(i);
+        match &self.end_normals {
+            Some(end) => n + s * (end.vec3(i) - n),
+            None => n,
+        }
+    }
+
+    fn vertices(&self, tri: usize, s: f64) -> (Point, Point, Point) {
+        let [a, b, c] = self.indices[tri];
+        (
+            self.position(a, s),
+            self.position(b, s),
+            self.position(c, s),
+        )
+    }
+
+    // box around the vertices once moved by mat, over both keyframes. tighter than turning bx
+    pub fn bounds(&self, mat: &glam::DMat4) -> Option<AABB> {
+        let count = (self.positions.len() / 3) as u32;
+        let points: Vec<Point> = [Some(&self.positions), self.end_positions.as_ref()]
+            .into_iter()
+            .flatten()
+            .flat_map(|store| (0..count).map(|i| store.vec3(i)))
+            .map(|p| Point::from(mat.transform_point3(p.into())))
+            .collect();
+        (!points.is_empty()).then(|| points_box(&points))
+    }
+
+    // the usemtl names in the file, in the order of the ids TriangleMesh's table goes by
+    pub fn material_names(&self) -> &[String] {
+        &self.material_names
+    }
+
+    fn material(&self, tri: usize) -> usize {
+        self.materials.get(tri).map_or(0, |m| *m as usize)
+    }
+
+    // closest triangle the ray hits, with its t and barycentric uv
+    fn closest(&self, r: &Ray, t_min: f64, mut t_max: f64) -> Option<(f64, usize, (f64, f64))> {
+        let s = self.fraction(r.time);
+        let mut closest = None;
+        let mut stack = [0usize; 64];
+        let mut len = if self.nodes.is_empty() { 0 } else { 1 };
+
+        while len > 0 {
+            len -= 1;
+            let n = stack[len];
+            let node = &self.nodes[n];
+            if !node.bx.hit(r, t_min, t_max) {
+                continue;
+            }
+
+            if node.count > 0 {
+                let first = node.offset as usize;
+                for tri in first..first + node.count as usize {
+                    if let Some((t, uv, _)) = triangle_intersect(r, self.vertices(tri, s)) {
+                        if t > t_min && t < t_max {
+                            t_max = t;
+                            closest = Some((t, tri, uv));
+                        }
+                    }
+                }
+            } else {
+                // nearer child on top so it's visited first and shrinks t_max for the other
+                let (near, far) = if r.dir[node.axis as usize] < 0.0 {
+                    (node.offset as usize, n + 1)
+                } else {
+                    (n + 1, node.offset as usize)
+                };
+                stack[len] = far;
+                stack[len + 1] = near;
+                len += 2;
+            }
+        }
+        closest
+    }
+
+    // the interpolated normal at barycentric uv, or the flat one where there isn't one
+    fn shading_normal(&self, tri: usize, (u, v): (f64, f64), s: f64) -> Vec3 {
+        let [a, b, c] = self.indices[tri];
+        let vs = self.vertices(tri, s);
+        let mut normal = (vs.1 - vs.0).cross(vs.2 - vs.0).unit();
+        if let Some(normals) = &self.normals {
+            let n = (1.0 - u - v) * self.normal(normals, a, s)
+                + u * self.normal(normals, b, s)
+                + v * self.normal(normals, c, s);
+            // faces without normals of their own keep the flat one
+            if n.length_squared() > 0.0 {
+                normal = n.unit();
+            }
+        }
+        normal
+    }
+
+    // texture coordinates at barycentric uv, which are just those when the mesh has none
+    fn surface_uv(&self, tri: usize, (u, v): (f64, f64)) -> (f64, f64) {
+        let [a, b, c] = self.indices[tri];
+        match &self.uvs {
+            Some(uvs) => {
+                let uv = (1.0 - u - v) * uvs.vec2(a) + u * uvs.vec2(b) + v * uvs.vec2(c);
+                (uv.x, uv.y)
             }
+            None => (u, v),
+        }
+    }
+
+    // a point spread evenly over the surface half way through the motion, from three numbers
+    // in [0, 1), with its normal and uv. the same numbers always give the same point
+    pub fn surface_point(&self, pick: f64, r1: f64, r2: f64) -> Option<(Point, Vec3, (f64, f64))> {
+        let area = *self.areas.last()?;
+        let tri = self
+            .areas
+            .partition_point(|a| *a < pick * area)
+            .min(self.areas.len() - 1);
+        let vs = self.vertices(tri, 0.5);
 
-            let v0 = obj.vers[face.verts[0].vert_idx - 1];
-            let v1 = obj.vers[face.verts[1].vert_idx - 1];
-            let v2 = obj.vers[face.verts[2].vert_idx - 1];
+        let sqrt_r = r1.sqrt();
+        let (u, v) = (sqrt_r * (1.0 - r2), sqrt_r * r2);
+        let p = (1.0 - u - v) * vs.0 + u * vs.1 + v * vs.2;
+        Some((
+            p,
+            self.shading_normal(tri, (u, v), 0.5),
+            self.surface_uv(tri, (u, v)),
+        ))
+    }
 
-            let mut uvs = None;
-            if face.verts[0].uv_idx != 0 {
-                let uv0 = obj.uvs[face.verts[0].uv_idx - 1];
-                let uv1 = obj.uvs[face.verts[1].uv_idx - 1];
-                let uv2 = obj.uvs[face.verts[2].uv_idx - 1];
-                uvs = Some((uv0, uv1, uv2))
+    // the hit on the closest triangle, and which triangle that was
+    fn hit_triangle(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<(HitRecord, usize)> {
+        let (t, tri, (u, v)) = self.closest(r, t_min, t_max)?;
+        let w = 1.0 - u - v;
+        let [a, b, c] = self.indices[tri];
+
+        let s = self.fraction(r.time);
+        let normal = self.shading_normal(tri, (u, v), s);
+
+        // the bitangent comes from the interpolated normal and tangent, roughly as in mikktspace
+        let (mut dpdu, mut dpdv) = (Vec3::default(), Vec3::default());
+        if let Some(tangents) = &self.tangents {
+            let (ta, bitangent) = tangents.tangent(a);
+            dpdu = w * ta + u * tangents.tangent(b).0 + v * tangents.tangent(c).0;
+            if dpdu.length_squared() > 0.0 {
+                dpdv = bitangent * normal.cross(dpdu.unit());
             }
+        }
+
+        let uv = self.surface_uv(tri, (u, v));
+        let color = self
+            .colors
+            .as_ref()
+            .map(|colors| w * colors.vec3(a) + u * colors.vec3(b) + v * colors.vec3(c));
+
+        let mut rec = HitRecord {
+            t,
+            p: r.at(t),
+            uv,
+            dpdu,
+            dpdv,
+            color,
+            ..Default::default()
+        };
+        rec.set_face_normal(r, normal);
+        Some((rec, tri))
+    }
+}
+
+// padded so a triangle lying in an axis plane still has some thickness
+fn points_box(points: &[Point]) -> AABB {
+    let (mut min, mut max) = (points[0], points[0]);
+    for p in &points[1..] {
+        min = Point::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z));
+        max = Point::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z));
+    }
+    let pad = Vec3::new(0.0001, 0.0001, 0.0001);
+    AABB::new(min - pad, max + pad)
+}
+
+// splits at the middle triangle along the axis the centres spread out most on, putting the
+// nodes depth first into `nodes` and the triangles in leaf order
+fn build_nodes(items: &mut [(usize, AABB, Point)], first: usize, nodes: &mut Vec<Node>) {
+    let bx = items
+        .iter()
+        .map(|item| item.1)
+        .reduce(AABB::from_surrounding)
+        .expect("some triangles");
+    let here = nodes.len();
+    nodes.push(Node {
+        bx,
+        offset: first as u32,
+        count: items.len() as u32,
+        axis: 0,
+    });
+    if items.len() <= LEAF_SIZE {
+        return;
+    }
+
+    let (mut lo, mut hi) = (items[0].2, items[0].2);
+    for (_, _, c) in items.iter() {
+        lo = Vec3::new(lo.x.min(c.x), lo.y.min(c.y), lo.z.min(c.z));
+        hi = Vec3::new(hi.x.max(c.x), hi.y.max(c.y), hi.z.max(c.z));
+    }
+    let extent = hi - lo;
+    let axis = if extent.x > extent.y && extent.x > extent.z {
+        0
+    } else if extent.y > extent.z {
+        1
+    } else {
+        2
+    };
+
+    let mid = items.len() / 2;
+    items.select_nth_unstable_by(mid, |a, b| a.2[axis].partial_cmp(&b.2[axis]).unwrap());
+    let (left, right) = items.split_at_mut(mid);
+    build_nodes(left, first, nodes);
+    let second = nodes.len();
+    build_nodes(right, first + mid, nodes);
+
+    nodes[here].offset = second as u32;
+    nodes[here].count = 0;
+    nodes[here].axis = axis as u8;
+}
+
+impl Hittable for Mesh {
+    fn bounding_box(&self, _time: (f64, f64)) -> Option<AABB> {
+        self.bx
+    }
+
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        self.hit_triangle(r, t_min, t_max).map(|(rec, _)| rec)
+    }
+
+    // points are picked evenly over the whole surface, so this is the pdf of the nearest
+    // triangle along v scaled by the total area
+    fn pdf_value(&self, o: &Point, v: &Point) -> f64 {
+        let area = self.areas.last().copied().unwrap_or(0.0);
+        let r = Ray::new(*o, *v).with_time(self.mid_time());
+        match self.hit(&r, 0.001, f64::MAX) {
+            Some(rec) if area > 0.0 => {
+                let dist_sqrd = rec.t * rec.t * v.length_squared();
+                let cos = (v.dot(rec.normal)).abs() / v.length();
 
-            let mut normals = None;
-            if face.verts[0].normal_idx != 0 {
-                let n0 = obj.normals[face.verts[0].normal_idx - 1];
-                let n1 = obj.normals[face.verts[1].normal_idx - 1];
-                let n2 = obj.normals[face.verts[2].normal_idx - 1];
-                normals = Some((n0, n1, n2))
+                dist_sqrd / (cos * area)
             }
+            _ => 0.0,
+        }
+    }
+
+    fn random(&self, o: &Vec3) -> Vec3 {
+        let (r0, r1, r2) = (
+            random_double_normal(),
+            random_double_normal(),
+            random_double_normal(),
+        );
+        match self.surface_point(r0, r1, r2) {
+            Some((p, _, _)) => p - *o,
+            None => Vec3::random_unit_vector(),
+        }
+    }
+}
+
+// how a triangle's points change along u and v, if its uvs aren't all in a line
+fn uv_derivatives(vs: (Point, Point, Point), uvs: (DVec2, DVec2, DVec2)) -> Option<(Vec3, Vec3)> {
+    let (e1, e2) = (vs.1 - vs.0, vs.2 - vs.0);
+    let (d1, d2) = (uvs.1 - uvs.0, uvs.2 - uvs.0);
+    let det = d1.x * d2.y - d1.y * d2.x;
+    if det.abs() < 1e-12 {
+        return None;
+    }
+    Some(((d2.y * e1 - d1.y * e2) / det, (d1.x * e2 - d2.x * e1) / det))
+}
+
+// what the corners around a vertex add up to, each weighted by its angle
+#[derive(Clone, Default)]
+struct TangentSum {
+    along_u: Vec3,
+    u_length: f64,
+    along_v: Vec3,
+    v_length: f64,
+    normal: Vec3,
+    angle: f64,
+}
+
+// four values per vertex, approximating mikktspace: every corner adds its face's direction
+// along u weighted by the angle there, and only the sum is made square to the vertex normal,
+// where mikktspace does each corner first and splits vertices whose bitangent signs differ.
+// normal maps baked by mikktspace tools can show faint seams. the last value is the length
+// along v, negative where the uvs are mirrored, so the bitangent is that times normal x tangent
+fn vertex_tangents(
+    positions: &Store,
+    normals: Option<&Store>,
+    uvs: &Store,
+    indices: &[[u32; 3]],
+) -> Vec<f64> {
+    let count = positions.len() / 3;
+    let mut sums = vec![TangentSum::default(); count];
+
+    for tri in indices {
+        let vs = tri.map(|i| positions.vec3(i));
+        let n = (vs[1] - vs[0]).cross(vs[2] - vs[0]);
+        let derivatives = uv_derivatives(
+            (vs[0], vs[1], vs[2]),
+            (uvs.vec2(tri[0]), uvs.vec2(tri[1]), uvs.vec2(tri[2])),
+        );
+        let (dpdu, dpdv) = match derivatives {
+            Some(d) if n.length_squared() > 0.0 => d,
+            _ => continue,
+        };
+
+        for i in 0..3 {
+            let (e1, e2) = (vs[(i + 1) % 3] - vs[i], vs[(i + 2) % 3] - vs[i]);
+            let angle = e1.unit().dot(e2.unit()).clamp(-1.0, 1.0).acos();
+            let sum = &mut sums[tri[i] as usize];
+            sum.along_u += angle * dpdu.unit();
+            sum.u_length += angle * dpdu.length();
+            sum.along_v += angle * dpdv.unit();
+            sum.v_length += angle * dpdv.length();
+            sum.normal += angle * n.unit();
+            sum.angle += angle;
+        }
+    }
+
+    let mut values = Vec::with_capacity(4 * count);
+    for (v, sum) in sums.iter().enumerate() {
+        let mut normal = normals.map_or(Vec3::default(), |ns| ns.vec3(v as u32));
+        if normal.length_squared() == 0.0 {
+            normal = sum.normal;
+        }
+        if sum.angle == 0.0 || normal.length_squared() == 0.0 {
+            values.extend([0.0; 4]);
+            continue;
+        }
+        let normal = normal.unit();
+        let tangent = sum.along_u - normal.dot(sum.along_u) * normal;
+        if tangent.length_squared() == 0.0 {
+            values.extend([0.0; 4]);
+            continue;
+        }
 
-            tris.add(Arc::new(Object::Triangle(Triangle {
-                vs: (v0, v1, v2),
-                normals,
-                uvs,
-            })))
+        let tangent = tangent.unit() * (sum.u_length / sum.angle);
+        let mut bitangent = sum.v_length / sum.angle;
+        if normal.cross(tangent).dot(sum.along_v) < 0.0 {
+            bitangent = -bitangent;
         }
+        values.extend([tangent.x, tangent.y, tangent.z, bitangent]);
+    }
+    values
+}
 
-        let node = BVHNode::new(tris, (0.0, 0.0));
+// the start keyframe decides which corners share a vertex
+fn mesh_buffers(obj: &OBJ, end: Option<&OBJ>, options: &MeshOptions) -> Buffers {
+    if options.subdivisions > 0
+        || options.displacement.is_some()
+        || options.smooth_normals.is_some()
+    {
+        let end = end.map(|end| refined_corners(end, options));
+        Buffers::weld(refined_corners(obj, options), end)
+    } else {
+        Buffers::weld(obj_corners(obj), end.map(obj_corners))
+    }
+}
 
-        let bx = node.bounding_box((0.0, 0.0));
+fn obj_corners(obj: &OBJ) -> Vec<Face<(usize, usize, usize)>> {
+    obj.faces
+        .iter()
+        .map(|face| {
+            if face.verts.len() != 3 {
+                panic!("face info doesn't have 3 verts")
+            }
+
+            let corners = [0, 1, 2].map(|i| {
+                let idx = &face.verts[i];
+                Corner {
+                    key: (idx.vert_idx, idx.uv_idx, idx.normal_idx),
+                    p: obj.vers[idx.vert_idx - 1],
+                    normal: (idx.normal_idx != 0).then(|| obj.normals[idx.normal_idx - 1]),
+                    uv: (idx.uv_idx != 0).then(|| obj.uvs[idx.uv_idx - 1]),
+                    color: obj.colors.get(idx.vert_idx - 1).copied(),
+                }
+            });
+            (corners, face.material as u32)
+        })
+        .collect()
+}
 
-        Self { node, bx }
+type RefinedKey = (usize, Option<[u64; 3]>, Option<[u64; 2]>);
+
+fn refined_corners(obj: &OBJ, options: &MeshOptions) -> Vec<Face<RefinedKey>> {
+    let mut poly = PolyMesh::from_obj(obj);
+    if let Some(angle) = options.crease_angle {
+        poly.crease_by_angle(angle);
+    }
+    let mut poly = poly.subdivide(options.subdivisions);
+    if let Some(displacement) = &options.displacement {
+        poly = poly.displace(displacement);
+    }
+    let mut poly = poly.triangulate();
+    if let (None, Some(angle)) = (&poly.normals, options.smooth_normals) {
+        poly.normals = Some(poly.creased_normals(angle));
     }
+
+    // corners only share a vertex when their normal and uv match too
+    poly.faces
+        .iter()
+        .enumerate()
+        .map(|(f, face)| {
+            let corners = [0, 1, 2].map(|c| {
+                let normal = poly.normals.as_ref().map(|ns| ns[f][c]);
+                let uv = poly.uvs.as_ref().map(|uvs| uvs[f][c]);
+                Corner {
+                    key: (
+                        face[c],
+                        normal.map(|n| [n.x.to_bits(), n.y.to_bits(), n.z.to_bits()]),
+                        uv.map(|uv| [uv.x.to_bits(), uv.y.to_bits()]),
+                    ),
+                    p: poly.positions[face[c]],
+                    normal,
+                    uv,
+                    color: poly.colors.as_ref().map(|cs| cs[f][c]),
+                }
+            });
+            (corners, poly.materials.as_ref().map_or(0, |m| m[f]))
+        })
+        .collect()
 }
 
 pub struct TriangleMesh {
     pub mesh: Arc<Mesh>,
-    pub mat: Arc<Material>,
+    // looked up by the material id of each face, ids past the end use the first entry. meshes
+    // from OBJ files give id 0 to faces before any usemtl and 1 on to the usemtl names in order
+    pub mats: Vec<Arc<Material>>,
 }
 
 impl TriangleMesh {
     pub fn new(mesh: Arc<Mesh>, mat: Arc<Material>) -> Self {
-        TriangleMesh { mesh, mat }
+        TriangleMesh {
+            mesh,
+            mats: vec![mat],
+        }
+    }
+
+    pub fn with_materials(mesh: Arc<Mesh>, mats: Vec<Arc<Material>>) -> Self {
+        assert!(!mats.is_empty(), "a mesh needs at least one material");
+        TriangleMesh { mesh, mats }
+    }
+
+    // a material for each usemtl name, falling back to `default` for names not in the map and
+    // faces without a usemtl
+    pub fn from_names(
+        mesh: Arc<Mesh>,
+        by_name: &HashMap<String, Arc<Material>>,
+        default: Arc<Material>,
+    ) -> Self {
+        let named = mesh
+            .material_names()
+            .iter()
+            .map(|name| by_name.get(name).cloned().unwrap_or(default.clone()));
+        let mats = std::iter::once(default.clone()).chain(named).collect();
+        TriangleMesh { mesh, mats }
     }
 }
 
@@ -170,10 +794,11 @@ impl Hittable for TriangleMesh {
     }
 
     fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
-        match self.mesh.node.hit(r, t_min, t_max) {
-            Some(rec) => {
+        match self.mesh.hit_triangle(r, t_min, t_max) {
+            Some((rec, tri)) => {
                 let mut new_rec = rec;
-                new_rec.mat = self.mat.clone();
+                let id = self.mesh.material(tri);
+                new_rec.mat = self.mats.get(id).unwrap_or(&self.mats[0]).clone();
                 Some(new_rec)
             }
             _ => None,
@@ -181,41 +806,368 @@ impl Hittable for TriangleMesh {
     }
 
     fn pdf_value(&self, o: &Point, v: &Point) -> f64 {
-        self.mesh.node.pdf_value(o, v)
+        self.mesh.pdf_value(o, v)
     }
 
     fn random(&self, o: &Vec3) -> Vec3 {
-        self.mesh.node.random(o)
+        self.mesh.random(o)
     }
 }
 
+// watertight test from woop, benthin and wald. the triangle is moved into a space where the ray
+// starts at the origin and runs down +z, so a hit is just a 2d point in triangle test. edges are
+// worked out the same way from either side, so a ray through an edge shared by two triangles
+// always hits at least one of them
 pub fn triangle_intersect(r: &Ray, v: (Point, Point, Point)) -> Option<(f64, (f64, f64), Vec3)> {
-    let v0v1 = v.1 - v.0;
-    let v0v2 = v.2 - v.0;
-    let pvec = r.dir.cross(v0v2);
-    let det = v0v1.dot(pvec);
+    // the ray's biggest axis becomes z, swapping the other two to keep the winding
+    let d = r.dir;
+    let kz = if d.x.abs() > d.y.abs() && d.x.abs() > d.z.abs() {
+        0
+    } else if d.y.abs() > d.z.abs() {
+        1
+    } else {
+        2
+    };
+    let (mut kx, mut ky) = ((kz + 1) % 3, (kz + 2) % 3);
+    if d[kz] < 0.0 {
+        std::mem::swap(&mut kx, &mut ky);
+    }
+
+    // shear so the ray runs straight down z
+    let (sx, sy, sz) = (-d[kx] / d[kz], -d[ky] / d[kz], 1.0 / d[kz]);
+    let local = |p: Point| {
+        let p = p - r.orig;
+        (p[kx] + sx * p[kz], p[ky] + sy * p[kz], p[kz])
+    };
+    let (p0, p1, p2) = (local(v.0), local(v.1), local(v.2));
 
-    if det.abs() < f64::EPSILON {
+    // edge functions, each one the weight of the corner opposite its edge
+    let e0 = p1.0 * p2.1 - p1.1 * p2.0;
+    let e1 = p2.0 * p0.1 - p2.1 * p0.0;
+    let e2 = p0.0 * p1.1 - p0.1 * p1.0;
+    if (e0 < 0.0 || e1 < 0.0 || e2 < 0.0) && (e0 > 0.0 || e1 > 0.0 || e2 > 0.0) {
+        return None;
+    }
+    let det = e0 + e1 + e2;
+    if det == 0.0 {
         return None;
     }
 
-    let inv_det = 1.0 / det;
+    let t = (e0 * p0.2 + e1 * p1.2 + e2 * p2.2) * sz / det;
 
-    let t_vec = r.orig - v.0;
-    let u = t_vec.dot(pvec) * inv_det;
-    if u < 0.0 || u > 1.0 {
-        return None;
+    let normal = (v.1 - v.0).cross(v.2 - v.0).unit();
+
+    Some((t, (e1 / det, e2 / det), normal))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Arc};
+
+    use glam::DVec2;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    use super::{triangle_intersect, Buffers, Corner, Face, Mesh, MeshOptions, TriangleMesh};
+    use crate::{
+        hittable::{HitRecord, Hittable},
+        material::{Lambertain, Material, Scatterable},
+        ray::Ray,
+        subdivision::PolyMesh,
+        texture::{Texture, VertexColor},
+        vec3::{Color, Point, Vec3},
+    };
+
+    fn corner<K>(key: K, p: Point, uv: Option<DVec2>) -> Corner<K> {
+        Corner {
+            key,
+            p,
+            normal: None,
+            uv,
+            color: None,
+        }
     }
 
-    let q_vec = t_vec.cross(v0v1);
-    let v = r.dir.dot(q_vec) * inv_det;
-    if v < 0.0 || u + v > 1.0 {
-        return None;
+    // a strip of triangles sharing vertices with their neighbours
+    fn strip(points: &[Point]) -> Vec<Face<usize>> {
+        (0..points.len() - 2)
+            .map(|i| ([i, i + 1, i + 2].map(|v| corner(v, points[v], None)), 0))
+            .collect()
+    }
+
+    #[test]
+    fn bvh_matches_every_triangle() {
+        let points: Vec<_> = (0..200).map(|_| Point::random(-2.0, 2.0)).collect();
+
+        for single in [false, true] {
+            let buffers = Buffers::weld(strip(&points), None);
+            assert_eq!(buffers.positions.len(), 3 * points.len());
+            let mesh = Mesh::from_buffers(buffers, (0.0, 0.0), single);
+
+            for _ in 0..1000 {
+                let r = Ray::new(Point::random(-4.0, 4.0), Vec3::random(-1.0, 1.0));
+
+                let mut closest: Option<f64> = None;
+                for tri in 0..mesh.indices.len() {
+                    if let Some((t, _, _)) = triangle_intersect(&r, mesh.vertices(tri, 0.0)) {
+                        if t > 0.001 && t < closest.unwrap_or(f64::MAX) {
+                            closest = Some(t);
+                        }
+                    }
+                }
+
+                let found = mesh.hit(&r, 0.001, f64::MAX).map(|rec| rec.t);
+                assert_eq!(found, closest);
+            }
+        }
+    }
+
+    // a smoothed octahedron, nudged about so the edges don't line up with the axes
+    fn closed_mesh(rng: &mut ChaCha8Rng) -> PolyMesh {
+        let octahedron = PolyMesh {
+            positions: vec![
+                Point::new(1.0, 0.0, 0.0),
+                Point::new(-1.0, 0.0, 0.0),
+                Point::new(0.0, 1.0, 0.0),
+                Point::new(0.0, -1.0, 0.0),
+                Point::new(0.0, 0.0, 1.0),
+                Point::new(0.0, 0.0, -1.0),
+            ],
+            faces: vec![
+                vec![0, 2, 4],
+                vec![2, 1, 4],
+                vec![1, 3, 4],
+                vec![3, 0, 4],
+                vec![2, 0, 5],
+                vec![1, 2, 5],
+                vec![3, 1, 5],
+                vec![0, 3, 5],
+            ],
+            ..Default::default()
+        };
+        let mut mesh = octahedron.subdivide(3);
+        for p in mesh.positions.iter_mut() {
+            *p += 0.05
+                * Vec3::new(
+                    rng.gen_range(-1.0..1.0),
+                    rng.gen_range(-1.0..1.0),
+                    rng.gen_range(-1.0..1.0),
+                );
+        }
+        mesh
+    }
+
+    fn hits_mesh(mesh: &PolyMesh, r: &Ray) -> bool {
+        mesh.faces.iter().any(|face| {
+            let vs = (
+                mesh.positions[face[0]],
+                mesh.positions[face[1]],
+                mesh.positions[face[2]],
+            );
+            triangle_intersect(r, vs).is_some_and(|(t, _, _)| t > 0.0)
+        })
+    }
+
+    #[test]
+    fn no_gaps_at_edges_and_vertices() {
+        // seeded, so the jitter and the targets are the same every run
+        let mut rng = ChaCha8Rng::seed_from_u64(43);
+        let mesh = closed_mesh(&mut rng);
+        // all well inside, the jittered surface can come in to about 0.35 from the centre
+        let origins = [
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(0.13, -0.21, 0.07),
+            Point::new(-0.15, 0.1, 0.12),
+        ];
+
+        let mut targets = mesh.positions.clone();
+        for face in &mesh.faces {
+            for i in 0..3 {
+                let (a, b) = (mesh.positions[face[i]], mesh.positions[face[(i + 1) % 3]]);
+                targets.push(a + 0.5 * (b - a));
+                targets.push(a + rng.gen::<f64>() * (b - a));
+            }
+        }
+
+        // everything inside a closed mesh has to hit it, whichever way it looks
+        for orig in origins {
+            for target in &targets {
+                assert!(hits_mesh(&mesh, &Ray::new(orig, *target - orig)));
+            }
+        }
+
+        // and from outside, aiming at edges and vertices on the near side
+        for target in &targets {
+            let orig = 3.0 * *target + Vec3::new(0.1, 0.2, -0.1);
+            assert!(hits_mesh(&mesh, &Ray::new(orig, *target - orig)));
+        }
+    }
+
+    #[test]
+    fn barycentric_coordinates() {
+        let vs = (
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(2.0, 0.0, 0.0),
+            Point::new(0.0, 2.0, 0.0),
+        );
+        let r = Ray::new(Point::new(0.5, 0.25, 2.0), Vec3::new(0.0, 0.0, -0.5));
+        let (t, (u, v), normal) = triangle_intersect(&r, vs).unwrap();
+        assert!((t - 4.0).abs() < 1e-12);
+        assert!((u - 0.25).abs() < 1e-12 && (v - 0.125).abs() < 1e-12);
+        assert!((normal.z - 1.0).abs() < 1e-12);
+
+        let r = Ray::new(Point::new(1.5, 1.5, 2.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(triangle_intersect(&r, vs).is_none());
     }
 
-    let t = v0v2.dot(q_vec) * inv_det;
+    #[test]
+    fn tangents_follow_uvs() {
+        // unit square facing +z, with u running twice as fast along x and v down y
+        let corners = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+        let vs = corners.map(|(x, y)| {
+            let uv = DVec2::new(2.0 * x, 1.0 - y);
+            (Point::new(x, y, 0.0), uv)
+        });
+        let tris =
+            [[0, 1, 2], [0, 2, 3]].map(|tri| (tri.map(|v| corner(v, vs[v].0, Some(vs[v].1))), 0));
+        let mesh = Mesh::from_buffers(Buffers::weld(tris.into(), None), (0.0, 0.0), false);
+
+        let r = Ray::new(Point::new(0.3, 0.6, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        let rec = mesh.hit(&r, 0.001, f64::MAX).unwrap();
+        assert!((rec.uv.0 - 0.6).abs() < 1e-9 && (rec.uv.1 - 0.4).abs() < 1e-9);
+        assert!((rec.dpdu - Vec3::new(0.5, 0.0, 0.0)).length() < 1e-9);
+        assert!((rec.dpdv - Vec3::new(0.0, -1.0, 0.0)).length() < 1e-9);
+    }
 
-    let normal = v0v1.cross(v0v2).unit();
+    #[test]
+    fn keyframes_move_with_ray_time() {
+        // a strip that slides from z = 0 to z = 2 over the shutter, staying in the same place
+        // in x and y
+        let points: Vec<_> = (0..50)
+            .map(|i| Point::new(0.1 * i as f64, (i % 2) as f64, 0.0))
+            .collect();
+        let moved: Vec<_> = points
+            .iter()
+            .map(|p| *p + Vec3::new(0.0, 0.0, 2.0))
+            .collect();
+        let mesh = Mesh::from_buffers(
+            Buffers::weld(strip(&points), Some(strip(&moved))),
+            (1.0, 2.0),
+            false,
+        );
+
+        let bx = mesh.bx.unwrap();
+        assert!(bx.min.z < 0.0 && bx.max.z > 2.0);
+
+        for (time, z) in [(1.0, 0.0), (1.25, 0.5), (2.0, 2.0), (3.0, 2.0)] {
+            let r = Ray::new(Point::new(2.43, 0.4, -5.0), Vec3::new(0.0, 0.0, 1.0)).with_time(time);
+            let rec = mesh.hit(&r, 0.001, f64::MAX).unwrap();
+            assert!((rec.p.z - z).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn faces_pick_their_material() {
+        // alternate triangles along the strip use material 0, 1 and 2, with only two in the table
+        let points: Vec<_> = (0..12)
+            .map(|i| Point::new(0.5 * i as f64, (i % 2) as f64, 0.0))
+            .collect();
+        let mut faces = strip(&points);
+        for (i, face) in faces.iter_mut().enumerate() {
+            face.1 = (i % 3) as u32;
+        }
+        let mesh = Arc::new(Mesh::from_buffers(
+            Buffers::weld(faces, None),
+            (0.0, 1.0),
+            false,
+        ));
+        let mats: Vec<_> = (0..2)
+            .map(|i| {
+                Arc::new(Material::Lambertain(Lambertain::new(Vec3::new(
+                    i as f64, 0.0, 0.0,
+                ))))
+            })
+            .collect();
+        let tm = TriangleMesh::with_materials(mesh, mats.clone());
 
-    Some((t, (u, v), normal))
+        for i in 0..10 {
+            // the centroid of triangle i
+            let c = (points[i] + points[i + 1] + points[i + 2]) / 3.0;
+            let r = Ray::new(c - Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, 1.0));
+            let rec = tm.hit(&r, 0.001, f64::MAX).unwrap();
+            let expected = if i % 3 == 1 { &mats[1] } else { &mats[0] };
+            assert!(Arc::ptr_eq(&rec.mat, expected));
+        }
+    }
+
+    #[test]
+    fn faces_before_usemtl_get_the_default() {
+        let mut obj = String::new();
+        for i in 0..4 {
+            let x = 2.0 * i as f64;
+            obj += &format!("v {x} 0 0\nv {} 0 0\nv {x} 1 0\n", x + 1.0);
+        }
+        obj += "f 1 2 3\nusemtl red\nf 4 5 6\nusemtl green\nf 7 8 9\nusemtl red\nf 10 11 12\n";
+        let path = std::env::temp_dir().join("faces_before_usemtl.obj");
+        std::fs::write(&path, obj).unwrap();
+
+        let lambertian = || Arc::new(Material::Lambertain(Lambertain::default()));
+        let (default, red, green) = (lambertian(), lambertian(), lambertian());
+        let by_name = HashMap::from([
+            ("red".to_string(), red.clone()),
+            ("green".to_string(), green.clone()),
+        ]);
+
+        // straight from the obj, and by way of a PolyMesh
+        for options in [
+            MeshOptions::default(),
+            MeshOptions {
+                smooth_normals: Some(30.0),
+                ..Default::default()
+            },
+        ] {
+            let mesh = Arc::new(Mesh::with_options(path.display().to_string(), options));
+            let tm = TriangleMesh::from_names(mesh, &by_name, default.clone());
+            for (i, expected) in [&default, &red, &green, &red].into_iter().enumerate() {
+                let c = Point::new(2.0 * i as f64 + 0.3, 0.3, -1.0);
+                let rec = tm.hit(&Ray::new(c, Vec3::new(0.0, 0.0, 1.0)), 0.001, f64::MAX);
+                assert!(Arc::ptr_eq(&rec.unwrap().mat, expected));
+            }
+        }
+    }
+
+    #[test]
+    fn vertex_colors_reach_albedo() {
+        let points = [
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(2.0, 0.0, 0.0),
+            Point::new(0.0, 2.0, 0.0),
+        ];
+        let colors = [
+            Color::new(1.0, 0.0, 0.0),
+            Color::new(0.0, 1.0, 0.0),
+            Color::new(0.0, 0.0, 1.0),
+        ];
+        let corners = [0, 1, 2].map(|v| Corner {
+            color: Some(colors[v]),
+            ..corner(v, points[v], None)
+        });
+        let mesh = Mesh::from_buffers(Buffers::weld(vec![(corners, 0)], None), (0.0, 0.0), true);
+
+        // a quarter of the way along the first edge and an eighth along the second
+        let r = Ray::new(Point::new(0.5, 0.25, 2.0), Vec3::new(0.0, 0.0, -1.0));
+        let rec = mesh.hit(&r, 0.001, f64::MAX).unwrap();
+        let expected = Color::new(0.625, 0.25, 0.125);
+        assert!((rec.color.unwrap() - expected).length() < 1e-6);
+
+        let grey = Color::new(0.5, 0.5, 0.5);
+        let texture = Arc::new(Texture::VertexColor(VertexColor::new(grey)));
+        let albedo = Lambertain::from_texture(texture);
+        let scattered = albedo.scatter(&r, &rec).unwrap();
+        assert!((scattered.attenuation - expected).length() < 1e-6);
+
+        // anything without vertex colours gets the fallback
+        let rec = HitRecord::default();
+        assert!((albedo.scatter(&r, &rec).unwrap().attenuation - grey).length() < 1e-12);
+    }
 }
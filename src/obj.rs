@@ -24,11 +24,13 @@ impl From<Vec<usize>> for FaceIdx {
 
         if value.len() >= 1 {
             vert_idx = value[0];
-        } else if value.len() >= 2 {
+        }
+        if value.len() >= 2 {
             uv_idx = value[1]
-        } else if value.len() >= 3 {
+        }
+        if value.len() >= 3 {
             normal_idx = value[2];
-        };
+        }
 
         Self {
             vert_idx,
@@ -40,6 +42,9 @@ impl From<Vec<usize>> for FaceIdx {
 
 pub struct FaceInfo {
     pub verts: Vec<FaceIdx>,
+    // 0 for faces before any usemtl, otherwise one past the index of the last usemtl's name in
+    // OBJ::materials
+    pub material: usize,
 }
 
 pub struct OBJ {
@@ -47,6 +52,10 @@ pub struct OBJ {
     pub faces: Vec<FaceInfo>,
     pub uvs: Vec<DVec2>,
     pub normals: Vec<Vec3>,
+    // one per vertex from `v x y z r g b` lines, empty unless every vertex has one
+    pub colors: Vec<Vec3>,
+    // usemtl names in the order they first appear
+    pub materials: Vec<String>,
 }
 
 pub fn load_obj(path: String) -> Result<OBJ, Box<dyn Error>> {
@@ -57,6 +66,9 @@ pub fn load_obj(path: String) -> Result<OBJ, Box<dyn Error>> {
     let mut faces = vec![];
     let mut uvs = vec![];
     let mut normals = vec![];
+    let mut colors = vec![];
+    let mut materials: Vec<String> = vec![];
+    let mut material = 0;
 
     for line in reader.lines() {
         let str = line.expect("unable to process line");
@@ -67,7 +79,12 @@ pub fn load_obj(path: String) -> Result<OBJ, Box<dyn Error>> {
         match parts[0] {
             "v" => {
                 let v = parse_parts::<f64>(&parts[1..]);
-                vers.push(Vec3::new(v[0], v[1], v[2]))
+                vers.push(Vec3::new(v[0], v[1], v[2]));
+                // the colour is the last three values, after an optional w
+                if v.len() >= 6 {
+                    let c = &v[v.len() - 3..];
+                    colors.push(Vec3::new(c[0], c[1], c[2]));
+                }
             }
             "vt" => {
                 let v = parse_parts(&parts[1..]);
@@ -88,16 +105,31 @@ pub fn load_obj(path: String) -> Result<OBJ, Box<dyn Error>> {
                     })
                     .map(FaceIdx::from)
                     .collect::<Vec<FaceIdx>>();
-                faces.push(FaceInfo { verts: v })
+                faces.push(FaceInfo { verts: v, material })
+            }
+            "usemtl" => {
+                let name = parts[1..].join(" ");
+                material = match materials.iter().position(|m| *m == name) {
+                    Some(i) => i + 1,
+                    None => {
+                        materials.push(name);
+                        materials.len()
+                    }
+                };
             }
             _ => continue,
         };
     }
+    if colors.len() != vers.len() {
+        colors.clear();
+    }
     Ok(OBJ {
         vers,
         faces,
         normals,
         uvs,
+        colors,
+        materials,
     })
 }
 
@@ -3,12 +3,20 @@ use std::{sync::Arc, vec};
 use crate::{
     aabb::AABB,
     bvh::BVHNode,
-    hittable::{FlipFace, HitRecord, Hittable, MatTransform},
+    csg::Csg,
+    curve::Curve,
+    heightfield::Heightfield,
+    hittable::{FlipFace, HitRecord, Hittable, MatTransform, MotionTransform},
     hittable_list::HittableList,
     material::Material,
-    mesh::{Triangle, TriangleMesh},
+    mesh::TriangleMesh,
+    patch::BezierPatch,
+    quad::Quad,
+    quadric::{Cone, Cylinder, Disk, Paraboloid},
     rect::{RectBox, XYRect, XZRect, YZRect},
+    sdf::SDFObject,
     sphere::{MovingSphere, Sphere},
+    torus::Torus,
 };
 
 pub enum Object {
@@ -20,11 +28,22 @@ pub enum Object {
     YZRect(YZRect),
     XZRect(XZRect),
     RectBox(RectBox),
+    Quad(Quad),
+    Disk(Disk),
+    Cylinder(Cylinder),
+    Cone(Cone),
+    Paraboloid(Paraboloid),
+    Torus(Torus),
+    SDF(SDFObject),
+    Csg(Csg),
+    BezierPatch(BezierPatch),
+    Curve(Curve),
+    Heightfield(Heightfield),
     TriangleMesh(TriangleMesh),
-    Triangle(Triangle),
 
     FlipFace(FlipFace),
     MatTransform(MatTransform),
+    MotionTransform(MotionTransform),
 }
 
 impl Default for Object {
@@ -48,22 +67,25 @@ impl Object {
         match self {
             Object::FlipFace(ff) => ff.ptr.is_light(),
             Object::MatTransform(mt) => mt.ptr.is_light(),
+            Object::MotionTransform(mt) => mt.ptr.is_light(),
+            Object::TriangleMesh(tm) => tm.mats.iter().any(|m| is_light_material(m)),
             _ => {
                 let mat = match self {
                     Object::XZRect(r) => Some(r.mat.clone()),
                     Object::XYRect(r) => Some(r.mat.clone()),
+                    Object::YZRect(r) => Some(r.mat.clone()),
+                    Object::Quad(q) => Some(q.mat.clone()),
+                    Object::Disk(d) => Some(d.mat.clone()),
+                    Object::Cylinder(c) => Some(c.mat.clone()),
+                    Object::Cone(c) => Some(c.mat.clone()),
+                    Object::Paraboloid(p) => Some(p.mat.clone()),
+                    Object::Torus(t) => Some(t.mat.clone()),
                     Object::Sphere(r) => Some(r.mat.clone()),
-                    Object::TriangleMesh(tm) => Some(tm.mat.clone()),
                     _ => None,
                 };
 
                 match mat {
-                    Some(m) => match *m {
-                        Material::DiffuseLight(_) => true,
-                        Material::Dielectric(_) => true,
-                        Material::Metal(_) => true,
-                        _ => false,
-                    },
+                    Some(m) => is_light_material(&m),
                     None => false,
                 }
             }
@@ -82,10 +104,21 @@ impl Hittable for Object {
             Object::YZRect(rect) => rect.hit(r, t_min, t_max),
             Object::XZRect(rect) => rect.hit(r, t_min, t_max),
             Object::RectBox(rect) => rect.hit(r, t_min, t_max),
+            Object::Quad(quad) => quad.hit(r, t_min, t_max),
+            Object::Disk(d) => d.hit(r, t_min, t_max),
+            Object::Cylinder(c) => c.hit(r, t_min, t_max),
+            Object::Cone(c) => c.hit(r, t_min, t_max),
+            Object::Paraboloid(p) => p.hit(r, t_min, t_max),
+            Object::Torus(t) => t.hit(r, t_min, t_max),
+            Object::SDF(s) => s.hit(r, t_min, t_max),
+            Object::Csg(c) => c.hit(r, t_min, t_max),
+            Object::BezierPatch(bp) => bp.hit(r, t_min, t_max),
+            Object::Curve(c) => c.hit(r, t_min, t_max),
+            Object::Heightfield(hf) => hf.hit(r, t_min, t_max),
             Object::FlipFace(ff) => ff.hit(r, t_min, t_max),
             Object::MatTransform(mt) => mt.hit(r, t_min, t_max),
+            Object::MotionTransform(mt) => mt.hit(r, t_min, t_max),
             Object::TriangleMesh(tm) => tm.hit(r, t_min, t_max),
-            Object::Triangle(tm) => tm.hit(r, t_min, t_max),
         }
     }
 
@@ -99,10 +132,21 @@ impl Hittable for Object {
             Object::YZRect(rect) => rect.bounding_box(time),
             Object::XZRect(rect) => rect.bounding_box(time),
             Object::RectBox(rect) => rect.bounding_box(time),
+            Object::Quad(quad) => quad.bounding_box(time),
+            Object::Disk(d) => d.bounding_box(time),
+            Object::Cylinder(c) => c.bounding_box(time),
+            Object::Cone(c) => c.bounding_box(time),
+            Object::Paraboloid(p) => p.bounding_box(time),
+            Object::Torus(t) => t.bounding_box(time),
+            Object::SDF(s) => s.bounding_box(time),
+            Object::Csg(c) => c.bounding_box(time),
+            Object::BezierPatch(bp) => bp.bounding_box(time),
+            Object::Curve(c) => c.bounding_box(time),
+            Object::Heightfield(hf) => hf.bounding_box(time),
             Object::FlipFace(ff) => ff.bounding_box(time),
             Object::MatTransform(mt) => mt.bounding_box(time),
+            Object::MotionTransform(mt) => mt.bounding_box(time),
             Object::TriangleMesh(tm) => tm.bounding_box(time),
-            Object::Triangle(tm) => tm.bounding_box(time),
         }
     }
 
@@ -116,10 +160,21 @@ impl Hittable for Object {
             Object::YZRect(rect) => rect.pdf_value(o, v),
             Object::XZRect(rect) => rect.pdf_value(o, v),
             Object::RectBox(rect) => rect.pdf_value(o, v),
+            Object::Quad(quad) => quad.pdf_value(o, v),
+            Object::Disk(d) => d.pdf_value(o, v),
+            Object::Cylinder(c) => c.pdf_value(o, v),
+            Object::Cone(c) => c.pdf_value(o, v),
+            Object::Paraboloid(p) => p.pdf_value(o, v),
+            Object::Torus(t) => t.pdf_value(o, v),
+            Object::SDF(s) => s.pdf_value(o, v),
+            Object::Csg(c) => c.pdf_value(o, v),
+            Object::BezierPatch(bp) => bp.pdf_value(o, v),
+            Object::Curve(c) => c.pdf_value(o, v),
+            Object::Heightfield(hf) => hf.pdf_value(o, v),
             Object::FlipFace(ff) => ff.pdf_value(o, v),
             Object::MatTransform(mt) => mt.pdf_value(o, v),
+            Object::MotionTransform(mt) => mt.pdf_value(o, v),
             Object::TriangleMesh(mt) => mt.pdf_value(o, v),
-            Object::Triangle(mt) => mt.pdf_value(o, v),
         }
     }
 
@@ -133,10 +188,31 @@ impl Hittable for Object {
             Object::YZRect(rect) => rect.random(o),
             Object::XZRect(rect) => rect.random(o),
             Object::RectBox(rect) => rect.random(o),
+            Object::Quad(quad) => quad.random(o),
+            Object::Disk(d) => d.random(o),
+            Object::Cylinder(c) => c.random(o),
+            Object::Cone(c) => c.random(o),
+            Object::Paraboloid(p) => p.random(o),
+            Object::Torus(t) => t.random(o),
+            Object::SDF(s) => s.random(o),
+            Object::Csg(c) => c.random(o),
+            Object::BezierPatch(bp) => bp.random(o),
+            Object::Curve(c) => c.random(o),
+            Object::Heightfield(hf) => hf.random(o),
             Object::FlipFace(ff) => ff.random(o),
             Object::MatTransform(mt) => mt.random(o),
+            Object::MotionTransform(mt) => mt.random(o),
             Object::TriangleMesh(mt) => mt.random(o),
-            Object::Triangle(mt) => mt.random(o),
         }
     }
 }
+
+// materials whose objects get sampled directly, either as lights or for the caustics through them
+fn is_light_material(m: &Material) -> bool {
+    match m.base() {
+        Material::DiffuseLight(_) => true,
+        Material::Dielectric(_) => true,
+        Material::Metal(_) => true,
+        _ => false,
+    }
+}
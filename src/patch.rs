@@ -0,0 +1,377 @@
+use std::{error::Error, fs, path::Path, sync::Arc};
+
+use crate::{
+    aabb::AABB,
+    bvh::BVHNode,
+    hittable::{HitRecord, Hittable},
+    hittable_list::HittableList,
+    material::Material,
+    mesh::triangle_intersect,
+    object::Object,
+    ray::Ray,
+    vec3::{Point, Vec3},
+};
+
+// rows of control points along v, each running along u
+pub type ControlPoints = [[Point; 4]; 4];
+
+// cells per side the patch is split into to find a starting point for newton's method
+const GRID: usize = 8;
+const NEWTON_STEPS: usize = 8;
+
+struct Cell {
+    bx: AABB,
+    u: (f64, f64),
+    v: (f64, f64),
+    // the patch at the cell's corners, going round from (u.0, v.0)
+    corners: [Point; 4],
+}
+
+// bicubic bezier patch, intersected exactly by newton's method from a guess off a flat
+// approximation of the patch. uv is the patch's own parameterisation
+pub struct BezierPatch {
+    cp: ControlPoints,
+    cells: Vec<Cell>,
+    bx: AABB,
+    pub mat: Arc<Material>,
+}
+
+fn bernstein(t: f64) -> [f64; 4] {
+    let s = 1.0 - t;
+    [s * s * s, 3.0 * s * s * t, 3.0 * s * t * t, t * t * t]
+}
+
+fn bernstein_derivative(t: f64) -> [f64; 4] {
+    let s = 1.0 - t;
+    [
+        -3.0 * s * s,
+        3.0 * s * s - 6.0 * s * t,
+        6.0 * s * t - 3.0 * t * t,
+        3.0 * t * t,
+    ]
+}
+
+// the curve with each de casteljau level taken at a different parameter. taking the levels at
+// a and b gives the control points of the piece of the curve between them
+fn blossom(c: &[Point; 4], ts: [f64; 3]) -> Point {
+    let lerp = |a: Point, b: Point, t: f64| (1.0 - t) * a + t * b;
+    let l1 = [
+        lerp(c[0], c[1], ts[0]),
+        lerp(c[1], c[2], ts[0]),
+        lerp(c[2], c[3], ts[0]),
+    ];
+    let l2 = [lerp(l1[0], l1[1], ts[1]), lerp(l1[1], l1[2], ts[1])];
+    lerp(l2[0], l2[1], ts[2])
+}
+
+fn sub_curve(c: &[Point; 4], a: f64, b: f64) -> [Point; 4] {
+    [
+        blossom(c, [a, a, a]),
+        blossom(c, [a, a, b]),
+        blossom(c, [a, b, b]),
+        blossom(c, [b, b, b]),
+    ]
+}
+
+fn surrounding(points: impl Iterator<Item = Point>) -> AABB {
+    let mut min = Point::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+    let mut max = -min;
+    for p in points {
+        min = Point::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z));
+        max = Point::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z));
+    }
+    // pad so flat patches still have some thickness
+    let pad = Vec3::new(0.0001, 0.0001, 0.0001);
+    AABB::new(min - pad, max + pad)
+}
+
+impl BezierPatch {
+    pub fn new(cp: ControlPoints, mat: Arc<Material>) -> Self {
+        // the patch lies inside the hull of its control points, and so does every piece of it
+        let mut cells = Vec::with_capacity(GRID * GRID);
+        for j in 0..GRID {
+            let v = (j as f64 / GRID as f64, (j + 1) as f64 / GRID as f64);
+            let rows: Vec<[Point; 4]> = (0..4)
+                .map(|i| sub_curve(&[cp[0][i], cp[1][i], cp[2][i], cp[3][i]], v.0, v.1))
+                .collect();
+
+            for i in 0..GRID {
+                let u = (i as f64 / GRID as f64, (i + 1) as f64 / GRID as f64);
+                let piece: Vec<Point> = (0..4)
+                    .flat_map(|k| {
+                        sub_curve(&[rows[0][k], rows[1][k], rows[2][k], rows[3][k]], u.0, u.1)
+                    })
+                    .collect();
+                // the end points of the pieces lie on the patch
+                cells.push(Cell {
+                    bx: surrounding(piece.iter().copied()),
+                    u,
+                    v,
+                    corners: [piece[0], piece[3], piece[15], piece[12]],
+                });
+            }
+        }
+
+        Self {
+            cp,
+            cells,
+            bx: surrounding(cp.iter().flatten().copied()),
+            mat,
+        }
+    }
+
+    // loads every patch in a file into a bvh, ready to be transformed like a mesh
+    pub fn load(path: String, mat: Arc<Material>) -> BVHNode {
+        let patches = load_patches(path).expect("invalid patch file");
+        let list = patches
+            .into_iter()
+            .map(|cp| Arc::new(Object::BezierPatch(BezierPatch::new(cp, mat.clone()))))
+            .collect();
+        BVHNode::new(HittableList::from_vec(list), (0.0, 0.0))
+    }
+
+    // point and the derivatives along u and v
+    fn eval(&self, u: f64, v: f64) -> (Point, Vec3, Vec3) {
+        let (bu, bv) = (bernstein(u), bernstein(v));
+        let (du, dv) = (bernstein_derivative(u), bernstein_derivative(v));
+
+        let mut p = Point::new_empty();
+        let mut pu = Vec3::new_empty();
+        let mut pv = Vec3::new_empty();
+        for j in 0..4 {
+            for i in 0..4 {
+                let c = self.cp[j][i];
+                p += bu[i] * bv[j] * c;
+                pu += du[i] * bv[j] * c;
+                pv += bu[i] * dv[j] * c;
+            }
+        }
+        (p, pu, pv)
+    }
+
+    fn normal(&self, u: f64, v: f64) -> Vec3 {
+        let (_, pu, pv) = self.eval(u, v);
+        let n = pu.cross(pv);
+        if n.length_squared() > 1e-20 {
+            return n.unit();
+        }
+        // collapsed edges, like the top of a lid, have no normal right on them
+        let nudge = |t: f64| t + if t < 0.5 { 1e-4 } else { -1e-4 };
+        let (_, pu, pv) = self.eval(nudge(u), nudge(v));
+        pu.cross(pv).unit()
+    }
+
+    // where the ray meets the patch near (u, v), as the crossing of two planes through the ray
+    fn newton(&self, r: &Ray, mut u: f64, mut v: f64) -> Option<(f64, f64, f64)> {
+        let d = r.dir.unit();
+        let n1 = if d.x.abs() > d.y.abs() && d.x.abs() > d.z.abs() {
+            Vec3::new(d.y, -d.x, 0.0)
+        } else {
+            Vec3::new(0.0, d.z, -d.y)
+        }
+        .unit();
+        let n2 = d.cross(n1);
+        let (d1, d2) = (-n1.dot(r.orig), -n2.dot(r.orig));
+        let tolerance = 1e-9 * (self.bx.max - self.bx.min).length();
+
+        for _ in 0..NEWTON_STEPS {
+            let (p, pu, pv) = self.eval(u, v);
+            let (f1, f2) = (n1.dot(p) + d1, n2.dot(p) + d2);
+            if f1.abs() < tolerance && f2.abs() < tolerance {
+                let t = (p - r.orig).dot(r.dir) / r.dir.length_squared();
+                return Some((t, u, v));
+            }
+
+            let (a, b, c, e) = (n1.dot(pu), n1.dot(pv), n2.dot(pu), n2.dot(pv));
+            let det = a * e - b * c;
+            if det.abs() < 1e-20 {
+                return None;
+            }
+            u -= (e * f1 - b * f2) / det;
+            v -= (a * f2 - c * f1) / det;
+        }
+        None
+    }
+
+    // starting point for newton's method from the two flat triangles across the cell
+    fn guess(&self, r: &Ray, cell: &Cell) -> (f64, f64) {
+        let (u0, u1, v0, v1) = (cell.u.0, cell.u.1, cell.v.0, cell.v.1);
+        let [p00, p10, p11, p01] = cell.corners;
+
+        if let Some((_, (a, b), _)) = triangle_intersect(r, (p00, p10, p11)) {
+            return (u0 + (a + b) * (u1 - u0), v0 + b * (v1 - v0));
+        }
+        if let Some((_, (a, b), _)) = triangle_intersect(r, (p00, p11, p01)) {
+            return (u0 + a * (u1 - u0), v0 + (a + b) * (v1 - v0));
+        }
+        (0.5 * (u0 + u1), 0.5 * (v0 + v1))
+    }
+}
+
+impl Hittable for BezierPatch {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        if !self.bx.hit(r, t_min, t_max) {
+            return None;
+        }
+
+        let mut closest = None;
+        let mut t_max = t_max;
+        for cell in &self.cells {
+            if !cell.bx.hit(r, t_min, t_max) {
+                continue;
+            }
+            let (u, v) = self.guess(r, cell);
+            if let Some((t, u, v)) = self.newton(r, u, v) {
+                let inside = (-1e-6..=1.0 + 1e-6).contains(&u) && (-1e-6..=1.0 + 1e-6).contains(&v);
+                if inside && t >= t_min && t <= t_max {
+                    t_max = t;
+                    closest = Some((t, u.clamp(0.0, 1.0), v.clamp(0.0, 1.0)));
+                }
+            }
+        }
+
+        let (t, u, v) = closest?;
+        let mut rec = HitRecord {
+            t,
+            p: r.at(t),
+            uv: (u, v),
+            mat: self.mat.clone(),
+            ..Default::default()
+        };
+        rec.set_face_normal(r, self.normal(u, v));
+        Some(rec)
+    }
+
+    fn bounding_box(&self, _time: (f64, f64)) -> Option<AABB> {
+        Some(self.bx)
+    }
+}
+
+// reads either the .bpt format, where each patch is its degrees (always 3 3) and then its 16
+// points, or newell's format of a patch count, 16 1-based vertex indices per patch, then a
+// vertex count and the vertices. numbers can be split by commas or whitespace
+pub fn load_patches(path: String) -> Result<Vec<ControlPoints>, Box<dyn Error>> {
+    let text = fs::read_to_string(&path)?;
+    let bpt = Path::new(&path).extension().is_some_and(|e| e == "bpt");
+    parse_patches(&text, bpt)
+}
+
+fn parse_patches(text: &str, bpt: bool) -> Result<Vec<ControlPoints>, Box<dyn Error>> {
+    let mut numbers = text
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse::<f64>());
+    let mut next =
+        || -> Result<f64, Box<dyn Error>> { Ok(numbers.next().ok_or("patch file ended early")??) };
+    let next_point = |next: &mut dyn FnMut() -> Result<f64, Box<dyn Error>>| {
+        Ok::<_, Box<dyn Error>>(Point::new(next()?, next()?, next()?))
+    };
+
+    let count = next()? as usize;
+    let mut patches = vec![[[Point::new_empty(); 4]; 4]; count];
+
+    if bpt {
+        for patch in &mut patches {
+            if (next()?, next()?) != (3.0, 3.0) {
+                return Err("only bicubic patches are supported".into());
+            }
+            for p in patch.iter_mut().flatten() {
+                *p = next_point(&mut next)?;
+            }
+        }
+    } else {
+        let mut indices = vec![0; count * 16];
+        for i in &mut indices {
+            *i = next()? as usize;
+        }
+        let vertices = (0..next()? as usize)
+            .map(|_| next_point(&mut next))
+            .collect::<Result<Vec<Point>, _>>()?;
+
+        for (patch, idx) in patches.iter_mut().zip(indices.chunks(16)) {
+            for (p, i) in patch.iter_mut().flatten().zip(idx) {
+                *p = *vertices
+                    .get(i.wrapping_sub(1))
+                    .ok_or("patch vertex out of range")?;
+            }
+        }
+    }
+    Ok(patches)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{parse_patches, BezierPatch};
+    use crate::{
+        hittable::Hittable,
+        material::{Lambertain, Material},
+        ray::Ray,
+        vec3::{Point, Vec3},
+    };
+
+    // bump over the unit square, raised in the middle
+    fn dome() -> BezierPatch {
+        let mut cp = [[Point::new_empty(); 4]; 4];
+        for (j, row) in cp.iter_mut().enumerate() {
+            for (i, p) in row.iter_mut().enumerate() {
+                let middle = (1..3).contains(&i) && (1..3).contains(&j);
+                *p = Point::new(
+                    i as f64 / 3.0,
+                    if middle { 1.0 } else { 0.0 },
+                    j as f64 / 3.0,
+                );
+            }
+        }
+        BezierPatch::new(cp, Arc::new(Material::Lambertain(Lambertain::default())))
+    }
+
+    #[test]
+    fn hits_on_the_surface() {
+        let patch = dome();
+        for (x, z) in [(0.5, 0.5), (0.1, 0.8), (0.95, 0.3)] {
+            let r = Ray::new(Point::new(x, 5.0, z), Vec3::new(0.0, -1.0, 0.0));
+            let rec = patch.hit(&r, 0.001, f64::MAX).unwrap();
+
+            let (p, _, _) = patch.eval(rec.uv.0, rec.uv.1);
+            assert!((p - rec.p).length() < 1e-6);
+            assert!((rec.p.x - x).abs() < 1e-6 && (rec.p.z - z).abs() < 1e-6);
+        }
+
+        // the middle of the dome is at (3/8 + 3/8)^2 of the height of its inner control points
+        let r = Ray::new(Point::new(0.5, 5.0, 0.5), Vec3::new(0.0, -1.0, 0.0));
+        let rec = patch.hit(&r, 0.001, f64::MAX).unwrap();
+        assert!((rec.p.y - 0.5625).abs() < 1e-6);
+        assert!(rec.normal.y > 0.999);
+
+        let r = Ray::new(Point::new(1.5, 5.0, 0.5), Vec3::new(0.0, -1.0, 0.0));
+        assert!(patch.hit(&r, 0.001, f64::MAX).is_none());
+    }
+
+    #[test]
+    fn parses_both_formats() {
+        let points: String = (0..16)
+            .map(|i| format!("{} {} 0\n", i % 4, i / 4))
+            .collect();
+        let bpt = parse_patches(&format!("1\n3 3\n{}", points), true).unwrap();
+
+        let indices: Vec<String> = (1..=16).map(|i| i.to_string()).collect();
+        let newell = format!(
+            "1\n{}\n16\n{}",
+            indices.join(","),
+            points.replace(' ', ", ")
+        );
+        let newell = parse_patches(&newell, false).unwrap();
+
+        assert_eq!(bpt.len(), 1);
+        assert_eq!(newell.len(), 1);
+        for (a, b) in bpt[0].iter().flatten().zip(newell[0].iter().flatten()) {
+            assert_eq!((a.x, a.y, a.z), (b.x, b.y, b.z));
+        }
+        assert_eq!(bpt[0][1][2].x, 2.0);
+        assert_eq!(bpt[0][1][2].y, 1.0);
+
+        assert!(parse_patches("1\n3 3\n0 0 0", true).is_err());
+    }
+}
@@ -0,0 +1,177 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::AABB,
+    hittable::{area_pdf_value, HitRecord, Hittable},
+    material::Material,
+    ray::Ray,
+    utils::random_double_normal,
+    vec3::{Point, Vec3},
+};
+
+// parallelogram with corner q and edges u and v. the front face is on the u x v side
+pub struct Quad {
+    q: Point,
+    u: Vec3,
+    v: Vec3,
+    // u x v / |u x v|^2, turns a point on the plane into its (alpha, beta) along u and v
+    w: Vec3,
+    normal: Vec3,
+    d: f64,
+    area: f64,
+    pub mat: Arc<Material>,
+}
+
+impl Quad {
+    pub fn new(q: Point, u: Vec3, v: Vec3, mat: Arc<Material>) -> Self {
+        let n = u.cross(v);
+        let normal = n.unit();
+
+        Self {
+            q,
+            u,
+            v,
+            w: n / n.dot(n),
+            normal,
+            d: normal.dot(q),
+            area: n.length(),
+            mat,
+        }
+    }
+}
+
+impl Hittable for Quad {
+    fn bounding_box(&self, _time: (f64, f64)) -> Option<AABB> {
+        let corners = [self.q + self.u, self.q + self.v, self.q + self.u + self.v];
+        let bbox = corners.iter().fold(AABB::new(self.q, self.q), |bx, c| {
+            AABB::from_surrounding(bx, AABB::new(*c, *c))
+        });
+
+        // pad so a quad lying in an axis plane still has some thickness
+        let pad = Vec3::new(0.0001, 0.0001, 0.0001);
+        Some(AABB::new(bbox.min - pad, bbox.max + pad))
+    }
+
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let denom = self.normal.dot(r.dir);
+        // parallel to the plane
+        if denom.abs() < 1e-8 {
+            return None;
+        }
+
+        let t = (self.d - self.normal.dot(r.orig)) / denom;
+        if t < t_min || t > t_max {
+            return None;
+        }
+
+        let p = r.at(t);
+        let planar = p - self.q;
+        let alpha = self.w.dot(planar.cross(self.v));
+        let beta = self.w.dot(self.u.cross(planar));
+
+        if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta) {
+            return None;
+        }
+
+        let mut rec = HitRecord {
+            t,
+            p,
+            uv: (alpha, beta),
+            mat: self.mat.clone(),
+            dpdu: self.u,
+            dpdv: self.v,
+            ..Default::default()
+        };
+        rec.set_face_normal(r, self.normal);
+        Some(rec)
+    }
+
+    fn pdf_value(&self, o: &Point, v: &Point) -> f64 {
+        area_pdf_value(self, self.area, o, v)
+    }
+
+    fn random(&self, o: &Vec3) -> Vec3 {
+        let random_point =
+            self.q + random_double_normal() * self.u + random_double_normal() * self.v;
+        random_point - *o
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{f64::consts::PI, sync::Arc};
+
+    use super::Quad;
+    use crate::{
+        hittable::Hittable,
+        material::{Lambertain, Material},
+        ray::Ray,
+        vec3::{Point, Vec3},
+    };
+
+    fn quad(q: Point, u: Vec3, v: Vec3) -> Quad {
+        let mat = Arc::new(Material::Lambertain(Lambertain::default()));
+        Quad::new(q, u, v, mat)
+    }
+
+    #[test]
+    fn hits_inside_with_uv_along_the_edges() {
+        // u x v points up +y
+        let quad = quad(
+            Point::new(1.0, 2.0, 3.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, -3.0),
+        );
+        let target = Point::new(1.5, 2.0, 1.5);
+
+        let down = Ray::new(target + Vec3::new(0.0, 4.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let rec = quad.hit(&down, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.t - 4.0).abs() < 1e-9);
+        assert!((rec.p - target).length() < 1e-9);
+        assert!((rec.uv.0 - 0.25).abs() < 1e-9 && (rec.uv.1 - 0.5).abs() < 1e-9);
+        assert!(rec.front_face);
+        assert!((rec.normal - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-9);
+
+        let up = Ray::new(target - Vec3::new(0.0, 4.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let rec = quad.hit(&up, 0.001, f64::INFINITY).unwrap();
+        assert!(!rec.front_face);
+        assert!((rec.normal - Vec3::new(0.0, -1.0, 0.0)).length() < 1e-9);
+
+        assert!(quad.hit(&down, 0.001, 3.9).is_none());
+        let outside = Ray::new(Point::new(3.5, 6.0, 1.5), Vec3::new(0.0, -1.0, 0.0));
+        assert!(quad.hit(&outside, 0.001, f64::INFINITY).is_none());
+        let parallel = Ray::new(Point::new(0.0, 2.0, 1.5), Vec3::new(1.0, 0.0, 0.0));
+        assert!(quad.hit(&parallel, 0.001, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn pdf_value_matches_random() {
+        // a 2 by 2 light 1 above the origin covers a solid angle of 2pi/3
+        let light = quad(
+            Point::new(-1.0, 1.0, -1.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 2.0),
+        );
+        let o = Point::default();
+        let n = 200_000;
+
+        // sampling the light and weighting by 1 / pdf gives back its solid angle
+        let mut solid_angle = 0.0;
+        for _ in 0..n {
+            let pdf = light.pdf_value(&o, &light.random(&o));
+            assert!(pdf > 0.0);
+            solid_angle += 1.0 / pdf;
+        }
+        solid_angle /= n as f64;
+        assert!((solid_angle - 2.0 * PI / 3.0).abs() < 0.01);
+
+        // and the pdf integrates to 1 over every direction
+        let total = (0..n)
+            .map(|_| light.pdf_value(&o, &Vec3::random_unit_vector()))
+            .sum::<f64>()
+            * 4.0
+            * PI
+            / n as f64;
+        assert!((total - 1.0).abs() < 0.05);
+    }
+}
@@ -0,0 +1,355 @@
+use std::{f64::consts::PI, sync::Arc};
+
+use crate::{
+    aabb::AABB,
+    hittable::{area_pdf_value, HitRecord, Hittable},
+    material::Material,
+    ray::Ray,
+    utils::{random_double_normal, solve_quadratic},
+    vec3::{Point, Vec3},
+};
+
+// these are all built around the y axis through `center`, use a MatTransform to orient them
+
+// angle around the y axis as a fraction of a full turn
+pub(crate) fn azimuth(x: f64, z: f64) -> f64 {
+    let phi = z.atan2(x);
+    (if phi < 0.0 { phi + 2.0 * PI } else { phi }) / (2.0 * PI)
+}
+
+fn record(r: &Ray, t: f64, outward_normal: Vec3, uv: (f64, f64), mat: &Arc<Material>) -> HitRecord {
+    let mut rec = HitRecord {
+        t,
+        p: r.at(t),
+        uv,
+        mat: mat.clone(),
+        ..Default::default()
+    };
+    rec.set_face_normal(r, outward_normal);
+    rec
+}
+
+// flat disk facing +y, with a hole in the middle if inner_radius > 0
+pub struct Disk {
+    center: Point,
+    radius: f64,
+    inner_radius: f64,
+    pub mat: Arc<Material>,
+}
+
+impl Disk {
+    pub fn new(center: Point, radius: f64, mat: Arc<Material>) -> Self {
+        Self {
+            center,
+            radius,
+            inner_radius: 0.0,
+            mat,
+        }
+    }
+
+    pub fn with_inner_radius(mut self, inner_radius: f64) -> Self {
+        self.inner_radius = inner_radius;
+        self
+    }
+
+    fn area(&self) -> f64 {
+        PI * (self.radius * self.radius - self.inner_radius * self.inner_radius)
+    }
+}
+
+impl Hittable for Disk {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        if r.dir.y.abs() < 1e-12 {
+            return None;
+        }
+        let t = (self.center.y - r.orig.y) / r.dir.y;
+        if t < t_min || t > t_max {
+            return None;
+        }
+
+        let p = r.at(t) - self.center;
+        let dist = (p.x * p.x + p.z * p.z).sqrt();
+        if dist > self.radius || dist < self.inner_radius {
+            return None;
+        }
+
+        let uv = (
+            azimuth(p.x, p.z),
+            (self.radius - dist) / (self.radius - self.inner_radius),
+        );
+        Some(record(r, t, Vec3::new(0.0, 1.0, 0.0), uv, &self.mat))
+    }
+
+    fn bounding_box(&self, _time: (f64, f64)) -> Option<AABB> {
+        let extent = Vec3::new(self.radius, 0.0001, self.radius);
+        Some(AABB::new(self.center - extent, self.center + extent))
+    }
+
+    fn pdf_value(&self, o: &Point, v: &Point) -> f64 {
+        area_pdf_value(self, self.area(), o, v)
+    }
+
+    fn random(&self, o: &Vec3) -> Vec3 {
+        let r2 = self.inner_radius * self.inner_radius;
+        let dist = (r2 + random_double_normal() * (self.radius * self.radius - r2)).sqrt();
+        let phi = 2.0 * PI * random_double_normal();
+        self.center + Vec3::new(dist * phi.cos(), 0.0, dist * phi.sin()) - *o
+    }
+}
+
+// stands on `center` and goes up `height`. open at both ends unless capped
+pub struct Cylinder {
+    center: Point,
+    radius: f64,
+    height: f64,
+    capped: bool,
+    pub mat: Arc<Material>,
+}
+
+impl Cylinder {
+    pub fn new(center: Point, radius: f64, height: f64, mat: Arc<Material>) -> Self {
+        Self {
+            center,
+            radius,
+            height,
+            capped: false,
+            mat,
+        }
+    }
+
+    pub fn with_caps(mut self) -> Self {
+        self.capped = true;
+        self
+    }
+
+    fn side_area(&self) -> f64 {
+        2.0 * PI * self.radius * self.height
+    }
+
+    fn area(&self) -> f64 {
+        let caps = if self.capped {
+            2.0 * PI * self.radius * self.radius
+        } else {
+            0.0
+        };
+        self.side_area() + caps
+    }
+}
+
+impl Hittable for Cylinder {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let o = r.orig - self.center;
+        let d = r.dir;
+        let mut closest = t_max;
+        let mut hit = None;
+
+        if let Some((t0, t1)) = solve_quadratic(
+            d.x * d.x + d.z * d.z,
+            2.0 * (o.x * d.x + o.z * d.z),
+            o.x * o.x + o.z * o.z - self.radius * self.radius,
+        ) {
+            for t in [t0, t1] {
+                let y = o.y + t * d.y;
+                if t >= t_min && t <= closest && (0.0..=self.height).contains(&y) {
+                    let p = o + t * d;
+                    let normal = Vec3::new(p.x, 0.0, p.z) / self.radius;
+                    let uv = (azimuth(p.x, p.z), y / self.height);
+                    hit = Some(record(r, t, normal, uv, &self.mat));
+                    closest = t;
+                    break;
+                }
+            }
+        }
+
+        if self.capped && d.y.abs() > 1e-12 {
+            for (y, normal) in [(0.0, -1.0), (self.height, 1.0)] {
+                let t = (y - o.y) / d.y;
+                if t < t_min || t > closest {
+                    continue;
+                }
+                let p = o + t * d;
+                let dist = (p.x * p.x + p.z * p.z).sqrt();
+                if dist <= self.radius {
+                    let uv = (azimuth(p.x, p.z), dist / self.radius);
+                    hit = Some(record(r, t, Vec3::new(0.0, normal, 0.0), uv, &self.mat));
+                    closest = t;
+                }
+            }
+        }
+
+        hit
+    }
+
+    fn bounding_box(&self, _time: (f64, f64)) -> Option<AABB> {
+        Some(AABB::new(
+            self.center - Vec3::new(self.radius, 0.0, self.radius),
+            self.center + Vec3::new(self.radius, self.height, self.radius),
+        ))
+    }
+
+    fn pdf_value(&self, o: &Point, v: &Point) -> f64 {
+        area_pdf_value(self, self.area(), o, v)
+    }
+
+    fn random(&self, o: &Vec3) -> Vec3 {
+        let phi = 2.0 * PI * random_double_normal();
+        let (dist, y) = if random_double_normal() * self.area() < self.side_area() {
+            (self.radius, random_double_normal() * self.height)
+        } else {
+            let y = if random_double_normal() < 0.5 {
+                0.0
+            } else {
+                self.height
+            };
+            (self.radius * random_double_normal().sqrt(), y)
+        };
+        self.center + Vec3::new(dist * phi.cos(), y, dist * phi.sin()) - *o
+    }
+}
+
+// base of `radius` on `center`, narrowing to a point `height` above it. the base is open
+pub struct Cone {
+    center: Point,
+    radius: f64,
+    height: f64,
+    pub mat: Arc<Material>,
+}
+
+impl Cone {
+    pub fn new(center: Point, radius: f64, height: f64, mat: Arc<Material>) -> Self {
+        Self {
+            center,
+            radius,
+            height,
+            mat,
+        }
+    }
+
+    fn area(&self) -> f64 {
+        PI * self.radius * (self.radius * self.radius + self.height * self.height).sqrt()
+    }
+}
+
+impl Hittable for Cone {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let o = r.orig - self.center;
+        let d = r.dir;
+        // x^2 + z^2 = k (h - y)^2
+        let k = (self.radius / self.height).powi(2);
+        let h = self.height - o.y;
+
+        let (t0, t1) = solve_quadratic(
+            d.x * d.x + d.z * d.z - k * d.y * d.y,
+            2.0 * (o.x * d.x + o.z * d.z + k * h * d.y),
+            o.x * o.x + o.z * o.z - k * h * h,
+        )?;
+
+        for t in [t0, t1] {
+            let p = o + t * d;
+            // the other half of the double cone is above the apex
+            if t >= t_min && t <= t_max && (0.0..=self.height).contains(&p.y) {
+                let normal = Vec3::new(p.x, k * (self.height - p.y), p.z).unit();
+                let uv = (azimuth(p.x, p.z), p.y / self.height);
+                return Some(record(r, t, normal, uv, &self.mat));
+            }
+        }
+        None
+    }
+
+    fn bounding_box(&self, _time: (f64, f64)) -> Option<AABB> {
+        Some(AABB::new(
+            self.center - Vec3::new(self.radius, 0.0, self.radius),
+            self.center + Vec3::new(self.radius, self.height, self.radius),
+        ))
+    }
+
+    fn pdf_value(&self, o: &Point, v: &Point) -> f64 {
+        area_pdf_value(self, self.area(), o, v)
+    }
+
+    fn random(&self, o: &Vec3) -> Vec3 {
+        // area grows linearly with the distance from the apex
+        let s = random_double_normal().sqrt();
+        let phi = 2.0 * PI * random_double_normal();
+        let dist = self.radius * s;
+        let p = Vec3::new(dist * phi.cos(), self.height * (1.0 - s), dist * phi.sin());
+        self.center + p - *o
+    }
+}
+
+// bowl with its lowest point on `center`, `radius` wide at `height` and open at the top
+pub struct Paraboloid {
+    center: Point,
+    radius: f64,
+    height: f64,
+    pub mat: Arc<Material>,
+}
+
+impl Paraboloid {
+    pub fn new(center: Point, radius: f64, height: f64, mat: Arc<Material>) -> Self {
+        Self {
+            center,
+            radius,
+            height,
+            mat,
+        }
+    }
+
+    // slope of the profile is k * distance from the axis
+    fn k(&self) -> f64 {
+        2.0 * self.height / (self.radius * self.radius)
+    }
+
+    // surface area out to `dist` from the axis
+    fn area_within(&self, dist: f64) -> f64 {
+        let k = self.k();
+        2.0 * PI / (3.0 * k * k) * ((1.0 + k * k * dist * dist).powf(1.5) - 1.0)
+    }
+}
+
+impl Hittable for Paraboloid {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let o = r.orig - self.center;
+        let d = r.dir;
+        // x^2 + z^2 = a y
+        let a = self.radius * self.radius / self.height;
+
+        let (t0, t1) = solve_quadratic(
+            d.x * d.x + d.z * d.z,
+            2.0 * (o.x * d.x + o.z * d.z) - a * d.y,
+            o.x * o.x + o.z * o.z - a * o.y,
+        )?;
+
+        for t in [t0, t1] {
+            let p = o + t * d;
+            if t >= t_min && t <= t_max && (0.0..=self.height).contains(&p.y) {
+                let normal = Vec3::new(2.0 * p.x, -a, 2.0 * p.z).unit();
+                let uv = (azimuth(p.x, p.z), p.y / self.height);
+                return Some(record(r, t, normal, uv, &self.mat));
+            }
+        }
+        None
+    }
+
+    fn bounding_box(&self, _time: (f64, f64)) -> Option<AABB> {
+        Some(AABB::new(
+            self.center - Vec3::new(self.radius, 0.0, self.radius),
+            self.center + Vec3::new(self.radius, self.height, self.radius),
+        ))
+    }
+
+    fn pdf_value(&self, o: &Point, v: &Point) -> f64 {
+        area_pdf_value(self, self.area_within(self.radius), o, v)
+    }
+
+    fn random(&self, o: &Vec3) -> Vec3 {
+        // invert area_within for a uniform share of the area
+        let k = self.k();
+        let w = 1.0 + random_double_normal() * ((1.0 + (k * self.radius).powi(2)).powf(1.5) - 1.0);
+        let dist = (w.powf(2.0 / 3.0) - 1.0).max(0.0).sqrt() / k;
+
+        let phi = 2.0 * PI * random_double_normal();
+        let y = 0.5 * k * dist * dist;
+        self.center + Vec3::new(dist * phi.cos(), y, dist * phi.sin()) - *o
+    }
+}
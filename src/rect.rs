@@ -2,11 +2,11 @@ use std::sync::Arc;
 
 use crate::{
     aabb::AABB,
-    hittable::{HitRecord, Hittable},
+    hittable::{area_pdf_value, HitRecord, Hittable},
     hittable_list::HittableList,
     material::Material,
     object::Object,
-    ray::Ray,
+    quad::Quad,
     utils::random_double,
     vec3::{Point, Vec3},
 };
@@ -61,6 +61,18 @@ impl Hittable for XYRect {
 
         Some(rec)
     }
+    fn pdf_value(&self, o: &Point, v: &Point) -> f64 {
+        let area = (self.x.1 - self.x.0) * (self.y.1 - self.y.0);
+        area_pdf_value(self, area, o, v)
+    }
+    fn random(&self, o: &Vec3) -> Vec3 {
+        let random_point = Point::new(
+            random_double(self.x.0, self.x.1),
+            random_double(self.y.0, self.y.1),
+            self.k,
+        );
+        random_point - *o
+    }
 }
 
 pub struct XZRect {
@@ -110,16 +122,8 @@ impl Hittable for XZRect {
         Some(rec)
     }
     fn pdf_value(&self, o: &Point, v: &Point) -> f64 {
-        match self.hit(&Ray::new(*o, *v), 0.001, f64::MAX) {
-            None => 0.0,
-            Some(rec) => {
-                let area = (self.x.1 - self.x.0) * (self.z.1 - self.z.0);
-                let dist_sqrd = rec.t * rec.t * v.length_squared();
-                let cos = (v.dot(rec.normal)).abs() / v.length();
-
-                dist_sqrd / (cos * area)
-            }
-        }
+        let area = (self.x.1 - self.x.0) * (self.z.1 - self.z.0);
+        area_pdf_value(self, area, o, v)
     }
     fn random(&self, o: &Vec3) -> Vec3 {
         let random_point = Point::new(
@@ -176,12 +180,24 @@ impl Hittable for YZRect {
         rec.p = r.at(t);
         Some(rec)
     }
+    fn pdf_value(&self, o: &Point, v: &Point) -> f64 {
+        let area = (self.y.1 - self.y.0) * (self.z.1 - self.z.0);
+        area_pdf_value(self, area, o, v)
+    }
+    fn random(&self, o: &Vec3) -> Vec3 {
+        let random_point = Point::new(
+            self.k,
+            random_double(self.y.0, self.y.1),
+            random_double(self.z.0, self.z.1),
+        );
+        random_point - *o
+    }
 }
 
 pub struct RectBox {
     box_min: Point,
     box_max: Point,
-    sides: HittableList,
+    pub(crate) sides: HittableList,
 }
 
 impl RectBox {
@@ -233,6 +249,32 @@ impl RectBox {
             sides,
         }
     }
+
+    // box spanned by the edges a, b and c from the corner origin, which don't have to line up
+    // with the axes. a right-handed basis gives outward facing sides
+    pub fn from_basis(origin: &Point, a: Vec3, b: Vec3, c: Vec3, mat: Arc<Material>) -> Self {
+        let mut sides = HittableList::new();
+        let far = *origin + a + b + c;
+
+        let faces = [
+            (*origin, b, a),
+            (*origin, a, c),
+            (*origin, c, b),
+            (far, -a, -b),
+            (far, -c, -a),
+            (far, -b, -c),
+        ];
+        for (q, u, v) in faces {
+            sides.add(Arc::new(Object::Quad(Quad::new(q, u, v, mat.clone()))));
+        }
+
+        let bbox = sides.bounding_box((0.0, 0.0)).unwrap();
+        Self {
+            box_min: bbox.min,
+            box_max: bbox.max,
+            sides,
+        }
+    }
 }
 
 impl Hittable for RectBox {
@@ -244,3 +286,57 @@ impl Hittable for RectBox {
         self.sides.hit(r, t_min, t_max)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::RectBox;
+    use crate::{
+        hittable::Hittable,
+        material::{Lambertain, Material},
+        ray::Ray,
+        vec3::{Point, Vec3},
+    };
+
+    #[test]
+    fn from_basis_sides_face_out() {
+        let origin = Point::new(1.0, -2.0, 0.5);
+        let (a, b, c) = (
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(0.5, 1.0, 0.0),
+            Vec3::new(0.0, 0.3, 1.5),
+        );
+        let mat = Arc::new(Material::Lambertain(Lambertain::default()));
+        let bx = RectBox::from_basis(&origin, a, b, c, mat);
+
+        let centre = origin + 0.5 * (a + b + c);
+        let far = origin + a + b + c;
+        let faces = [
+            origin + 0.5 * (a + b),
+            origin + 0.5 * (a + c),
+            origin + 0.5 * (b + c),
+            far - 0.5 * (a + b),
+            far - 0.5 * (a + c),
+            far - 0.5 * (b + c),
+        ];
+        for face in faces {
+            let out = face - centre;
+
+            let leaving = Ray::new(centre, out);
+            let rec = bx.hit(&leaving, 0.001, f64::INFINITY).unwrap();
+            assert!((rec.p - face).length() < 1e-9);
+            assert!(!rec.front_face && rec.normal.dot(out) < 0.0);
+
+            let arriving = Ray::new(face + 3.0 * out, -out);
+            let rec = bx.hit(&arriving, 0.001, f64::INFINITY).unwrap();
+            assert!((rec.p - face).length() < 1e-9);
+            assert!(rec.front_face && rec.normal.dot(out) > 0.0);
+        }
+
+        let bounds = bx.bounding_box((0.0, 0.0)).unwrap();
+        assert!(bounds.min.x <= 1.0 && bounds.max.x >= 3.5);
+        assert!(bounds.min.y <= -2.0 && bounds.max.y >= -0.7);
+        assert!(bounds.min.z <= 0.5 && bounds.max.z >= 2.0);
+    }
+}
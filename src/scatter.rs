@@ -0,0 +1,269 @@
+use std::{collections::HashMap, sync::Arc};
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    hittable::MatTransform,
+    hittable_list::HittableList,
+    mesh::Mesh,
+    object::Object,
+    texture::{Texture, TextureMat},
+    vec3::{Point, Vec3},
+};
+
+// where instances can go
+pub enum Region {
+    // the rectangle x.0..x.1 by z.0..z.1 on the plane y = k, with uvs running 0 to 1 across it
+    XZRect {
+        x: (f64, f64),
+        z: (f64, f64),
+        k: f64,
+    },
+    // the surface of a mesh, moved by the matrix the way it is in the scene
+    Surface(Arc<Mesh>, glam::DMat4),
+}
+
+impl Region {
+    // a point spread evenly over the region, with its normal and uv
+    fn sample(&self, rng: &mut ChaCha8Rng) -> Option<(Point, Vec3, (f64, f64))> {
+        match self {
+            Region::XZRect { x, z, k } => {
+                let (u, v) = (rng.gen::<f64>(), rng.gen::<f64>());
+                let p = Point::new(x.0 + u * (x.1 - x.0), *k, z.0 + v * (z.1 - z.0));
+                Some((p, Vec3::new(0.0, 1.0, 0.0), (u, v)))
+            }
+            Region::Surface(mesh, mat) => {
+                let (p, n, uv) = mesh.surface_point(rng.gen(), rng.gen(), rng.gen())?;
+                let p = Point::from(mat.transform_point3(p.into()));
+                let n = Vec3::from(mat.inverse().transpose().transform_vector3(n.into())).unit();
+                Some((p, n, uv))
+            }
+        }
+    }
+}
+
+// copies of one object strewn over a region. every copy is a transform around the same
+// Arc<Object>, so a mesh's triangles are only stored once however many there are
+pub struct Scatter {
+    pub count: usize,
+    // no two copies closer than this, by dart throwing for a poisson disk spread. 0 places
+    // them independently
+    pub min_distance: f64,
+    // how bright the texture is at a point is the chance of a copy being kept there
+    pub density: Option<Arc<Texture>>,
+    // degrees about the up direction, and uniform scale, each picked evenly from the range
+    pub rotation: (f64, f64),
+    pub scale: (f64, f64),
+    // stands copies along the surface normal rather than straight up +y
+    pub align_to_normal: bool,
+    pub seed: u64,
+}
+
+impl Default for Scatter {
+    fn default() -> Self {
+        Self {
+            count: 100,
+            min_distance: 0.0,
+            density: None,
+            rotation: (0.0, 360.0),
+            scale: (1.0, 1.0),
+            align_to_normal: false,
+            seed: 0,
+        }
+    }
+}
+
+// candidates tried for each copy before giving up on a crowded or sparse region
+const TRIES_PER_INSTANCE: usize = 30;
+
+impl Scatter {
+    // the same seed always gives the same copies. the object's origin is what goes on the
+    // surface, so models are best placed with their base there first
+    pub fn instances(&self, ptr: Arc<Object>, region: &Region) -> HittableList {
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed);
+        let mut placed = HittableList::new();
+        let mut grid = PointGrid::new(self.min_distance);
+
+        for _ in 0..self.count * TRIES_PER_INSTANCE {
+            if placed.objects.len() == self.count {
+                break;
+            }
+            let (p, n, uv) = match region.sample(&mut rng) {
+                Some(sample) => sample,
+                None => break,
+            };
+
+            if let Some(density) = &self.density {
+                let c = density.value(uv.0, uv.1, &p);
+                if rng.gen::<f64>() >= (c.x + c.y + c.z) / 3.0 {
+                    continue;
+                }
+            }
+            if self.min_distance > 0.0 && !grid.insert(p) {
+                continue;
+            }
+
+            let rotation = between(&mut rng, self.rotation).to_radians();
+            let scale = between(&mut rng, self.scale);
+            let up = if self.align_to_normal {
+                n
+            } else {
+                Vec3::new(0.0, 1.0, 0.0)
+            };
+            let turn = glam::DQuat::from_rotation_arc(glam::DVec3::Y, up.into())
+                * glam::DQuat::from_rotation_y(rotation);
+            let mat = glam::DMat4::from_scale_rotation_translation(
+                glam::DVec3::ONE * scale,
+                turn,
+                p.into(),
+            );
+
+            placed.add(Arc::new(Object::MatTransform(MatTransform::new(
+                mat,
+                ptr.clone(),
+            ))));
+        }
+        placed
+    }
+}
+
+fn between(rng: &mut ChaCha8Rng, range: (f64, f64)) -> f64 {
+    range.0 + rng.gen::<f64>() * (range.1 - range.0)
+}
+
+// points hashed into cells as wide as the spacing, so only the 27 cells around a new point
+// need checking
+struct PointGrid {
+    spacing: f64,
+    cells: HashMap<[i64; 3], Vec<Point>>,
+}
+
+impl PointGrid {
+    fn new(spacing: f64) -> Self {
+        Self {
+            spacing,
+            cells: HashMap::new(),
+        }
+    }
+
+    fn cell(&self, p: Point) -> [i64; 3] {
+        [p.x, p.y, p.z].map(|c| (c / self.spacing).floor() as i64)
+    }
+
+    // adds p unless it's too close to one already there
+    fn insert(&mut self, p: Point) -> bool {
+        let [x, y, z] = self.cell(p);
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    let near = self.cells.get(&[x + dx, y + dy, z + dz]);
+                    if near
+                        .into_iter()
+                        .flatten()
+                        .any(|q| (*q - p).length() < self.spacing)
+                    {
+                        return false;
+                    }
+                }
+            }
+        }
+        self.cells.entry([x, y, z]).or_default().push(p);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{Region, Scatter};
+    use crate::{
+        hittable::Hittable,
+        material::{Lambertain, Material},
+        object::Object,
+        sphere::Sphere,
+        texture::{CheckerTexture, SolidColor, Texture},
+        vec3::{Color, Point},
+    };
+
+    fn ball() -> Arc<Object> {
+        let mat = Arc::new(Material::Lambertain(Lambertain::default()));
+        Arc::new(Object::Sphere(Sphere::new(Point::default(), 0.1, mat)))
+    }
+
+    fn centres(scatter: &Scatter, region: &Region) -> Vec<Point> {
+        scatter
+            .instances(ball(), region)
+            .objects
+            .iter()
+            .map(|o| {
+                let bx = o.bounding_box((0.0, 0.0)).unwrap();
+                0.5 * (bx.min + bx.max)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn poisson_disk_keeps_its_distance() {
+        let region = Region::XZRect {
+            x: (0.0, 10.0),
+            z: (0.0, 10.0),
+            k: 1.0,
+        };
+        let scatter = Scatter {
+            count: 200,
+            min_distance: 0.5,
+            seed: 7,
+            ..Default::default()
+        };
+        let points = centres(&scatter, &region);
+        assert_eq!(points.len(), 200);
+        assert!(points.iter().all(|p| (p.y - 1.0).abs() < 1e-9));
+        for (i, p) in points.iter().enumerate() {
+            for q in &points[i + 1..] {
+                assert!((*p - *q).length() >= 0.5);
+            }
+        }
+
+        // the same seed gives the same layout
+        let again = centres(&scatter, &region);
+        assert!(points
+            .iter()
+            .zip(&again)
+            .all(|(p, q)| (*p - *q).length() == 0.0));
+    }
+
+    #[test]
+    fn density_texture_thins_copies_out() {
+        // off the y = 0 plane, where the checker's sines are all 0
+        let region = Region::XZRect {
+            x: (0.0, 1.0),
+            z: (0.0, 1.0),
+            k: 0.05,
+        };
+        let black = Arc::new(Texture::SolidColor(SolidColor::new(0.0, 0.0, 0.0)));
+        let none = Scatter {
+            density: Some(black),
+            ..Default::default()
+        };
+        assert!(centres(&none, &region).is_empty());
+
+        // a checker leaves the copies only where it's white
+        let checker = Arc::new(Texture::CheckerTexture(CheckerTexture::from_colors(
+            Color::new(1.0, 1.0, 1.0),
+            Color::new(0.0, 0.0, 0.0),
+        )));
+        let half = Scatter {
+            count: 50,
+            density: Some(checker),
+            ..Default::default()
+        };
+        let points = centres(&half, &region);
+        assert_eq!(points.len(), 50);
+        for p in points {
+            let sines = (p.x * 10.0).sin() * (p.y * 10.0).sin() * (p.z * 10.0).sin();
+            assert!(sines >= 0.0);
+        }
+    }
+}
@@ -1,13 +1,23 @@
 use std::{f64::consts::PI, sync::Arc};
 
 use crate::{
-    hittable::{FlipFace, MatTransform},
+    aabb::AABB,
+    animation::{CameraTrack, Interpolation, Track},
+    bvh::BVHNode,
+    camera::Shutter,
+    csg::{Csg, CsgOp},
+    curve::{Curve, CurveMode},
+    heightfield::Heightfield,
+    hittable::{FlipFace, MatTransform, MotionTransform, Placement},
     hittable_list::HittableList,
-    material::{Dielectric, DiffuseLight, Lambertain, Material, Metal},
-    mesh::{Mesh, TriangleMesh},
+    material::{Bumped, Dielectric, DiffuseLight, Lambertain, Material, Metal},
+    mesh::{Mesh, MeshOptions, TriangleMesh},
     object::Object,
     rect::{RectBox, XYRect, XZRect, YZRect},
+    scatter::{Region, Scatter},
+    sdf::{SDFObject, SDF},
     sphere::{MovingSphere, Sphere},
+    stereo::StereoConfig,
     texture::{CheckerTexture, ImageTexture, Texture},
     utils::{random_double, random_double_normal},
     vec3::{Color, Point, Vec3},
@@ -25,11 +35,25 @@ pub struct SceneConfig {
     pub image_height: usize,
     pub aspect_ratio: f64,
     pub dist_to_focus: f64,
+    pub autofocus: bool,
+    // prescription for a RealisticCamera, thin lens otherwise
+    pub lens_file: Option<String>,
+    // film diagonal in mm and scene units per mm, for the realistic camera
+    pub film_diagonal: f64,
+    pub lens_scale: f64,
+    pub shutter: Shutter,
+    // renders both eyes into the one image when set
+    pub stereo: Option<StereoConfig>,
     pub background: Color,
     pub samples_per_pixel: u32,
 }
 
 pub fn new_scene(choice: u32) -> SceneConfig {
+    new_scene_at(choice, 0.0)
+}
+
+// the scene as it is `time` seconds into its animation. static scenes ignore the time
+pub fn new_scene_at(choice: u32, time: f64) -> SceneConfig {
     let (mut cfg, world): (SceneConfig, HittableList) = match choice {
         1 => (
             SceneConfig {
@@ -37,6 +61,7 @@ pub fn new_scene(choice: u32) -> SceneConfig {
                 lookat: Point::new(0.0, 0.0, 0.0),
                 vfov: 20.0,
                 aperture: 0.1,
+                autofocus: true,
                 background: Color::new(0.7, 0.8, 1.0),
                 ..Default::default()
             },
@@ -96,6 +121,109 @@ pub fn new_scene(choice: u32) -> SceneConfig {
             },
             teapot_galore(),
         ),
+        9 => (
+            SceneConfig {
+                aspect_ratio: 1.0,
+                samples_per_pixel: 200,
+                background: Color::new_empty(),
+                lookfrom: Vec3::new(278.0, 278.0, -800.0),
+                lookat: Vec3::new(278.0, 278.0, 0.0),
+                vfov: 40.0,
+                autofocus: true,
+                dist_to_focus: 1000.0,
+                lens_file: Some("data/dgauss.50mm.dat".into()),
+                film_diagonal: 35.0,
+                lens_scale: 1.0,
+                ..Default::default()
+            },
+            cornell_box(),
+        ),
+        10 => turntable(time),
+        11 => (
+            SceneConfig {
+                aspect_ratio: 2.0,
+                samples_per_pixel: 200,
+                background: Color::new_empty(),
+                lookfrom: Vec3::new(278.0, 278.0, -800.0),
+                lookat: Vec3::new(278.0, 278.0, 0.0),
+                vfov: 40.0,
+                stereo: Some(StereoConfig {
+                    ipd: 30.0,
+                    convergence: 800.0,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            cornell_box(),
+        ),
+        12 => (
+            SceneConfig {
+                aspect_ratio: 1.0,
+                samples_per_pixel: 200,
+                background: Color::new_empty(),
+                lookfrom: Vec3::new(278.0, 278.0, -800.0),
+                lookat: Vec3::new(278.0, 278.0, 0.0),
+                vfov: 40.0,
+                ..Default::default()
+            },
+            sdf_cornell(),
+        ),
+        13 => (
+            SceneConfig {
+                aspect_ratio: 1.0,
+                samples_per_pixel: 200,
+                background: Color::new_empty(),
+                lookfrom: Vec3::new(278.0, 278.0, -800.0),
+                lookat: Vec3::new(278.0, 278.0, 0.0),
+                vfov: 40.0,
+                ..Default::default()
+            },
+            csg_cornell(),
+        ),
+        14 => (
+            SceneConfig {
+                aspect_ratio: 1.0,
+                samples_per_pixel: 200,
+                background: Color::new_empty(),
+                lookfrom: Vec3::new(278.0, 278.0, -800.0),
+                lookat: Vec3::new(278.0, 278.0, 0.0),
+                vfov: 40.0,
+                ..Default::default()
+            },
+            fur_cornell(),
+        ),
+        15 => (
+            SceneConfig {
+                lookfrom: Vec3::new(0.0, 7.0, -16.0),
+                lookat: Point::new(0.0, 1.0, 0.0),
+                background: Color::new(0.7, 0.8, 1.0),
+                vfov: 40.0,
+                ..Default::default()
+            },
+            terrain(),
+        ),
+        16 => (
+            SceneConfig {
+                lookfrom: Vec3::new(0.0, 2.0, -14.0),
+                lookat: Point::new(0.0, 0.0, 0.0),
+                background: Color::new(0.05, 0.05, 0.08),
+                vfov: 30.0,
+                ..Default::default()
+            },
+            bumpy_earth(),
+        ),
+        17 => (
+            SceneConfig {
+                aspect_ratio: 2.0,
+                samples_per_pixel: 200,
+                background: Color::new_empty(),
+                lookfrom: Vec3::new(278.0, 180.0, -250.0),
+                lookat: Vec3::new(278.0, 60.0, 220.0),
+                vfov: 28.0,
+                ..Default::default()
+            },
+            subdivided_cornell(),
+        ),
         8 | _ => (
             SceneConfig {
                 aspect_ratio: 1.0,
@@ -116,7 +244,21 @@ pub fn new_scene(choice: u32) -> SceneConfig {
     cfg.lights = lights_hittable.into();
     cfg.samples_per_pixel = cfg.samples_per_pixel.max(100);
     cfg.vup = Vec3::new(0.0, 1.0, 0.0);
-    cfg.dist_to_focus = 10.0;
+    cfg.dist_to_focus = if cfg.dist_to_focus == 0.0 {
+        10.0
+    } else {
+        cfg.dist_to_focus
+    };
+    cfg.film_diagonal = if cfg.film_diagonal == 0.0 {
+        35.0
+    } else {
+        cfg.film_diagonal
+    };
+    cfg.lens_scale = if cfg.lens_scale == 0.0 {
+        1.0
+    } else {
+        cfg.lens_scale
+    };
     cfg.aspect_ratio = if cfg.aspect_ratio == 0.0 {
         16.0 / 9.0
     } else {
@@ -132,31 +274,30 @@ fn teapot_galore() -> HittableList {
         0.73, 0.73, 0.73,
     ))));
 
+    // one teapot stood with its base on the origin, shared by every copy
     let mesh = Arc::new(Mesh::new("data/teapot.obj".into()));
+    let teapot = Arc::new(Object::MatTransform(MatTransform::placed(
+        Arc::new(Object::TriangleMesh(TriangleMesh::new(mesh, white))),
+        &Placement {
+            on_ground: true,
+            ..Default::default()
+        },
+    )));
 
-    for j in 0..5 {
-        for i in 0..5 {
-            let trans = glam::DVec3::new((i as f64) * 6.0, 1.0, (j as f64) * 6.0);
-
-            let rot = glam::DQuat::from_axis_angle(
-                Vec3::random_unit_vector().into(),
-                random_double(0.0, 2.0 * PI),
-            );
-
-            let teapot = Arc::new(Object::TriangleMesh(TriangleMesh::new(mesh.clone(), white.clone())));
-
-            let mesh_mat = glam::DMat4::from_scale_rotation_translation(
-                glam::DVec3::ONE * 1.0,
-                rot,
-                trans,
-            );
-
-            world.add(Arc::new(Object::MatTransform(MatTransform::new(
-                mesh_mat,
-                teapot.clone(),
-            ))));
-        }
-    }
+    let scatter = Scatter {
+        count: 40,
+        min_distance: 5.5,
+        scale: (0.6, 1.0),
+        seed: 1,
+        ..Default::default()
+    };
+    let region = Region::XZRect {
+        x: (-2.0, 32.0),
+        z: (-5.0, 25.0),
+        k: 0.0,
+    };
+    let teapots = scatter.instances(teapot, &region);
+    world.add(Arc::new(Object::BVHNode(BVHNode::new(teapots, (0.0, 0.0)))));
 
     let checker = Arc::new(Texture::CheckerTexture(CheckerTexture::from_colors(
         Color::new(0.2, 0.3, 0.1),
@@ -180,6 +321,65 @@ fn teapot_galore() -> HittableList {
     world
 }
 
+fn turntable(time: f64) -> (SceneConfig, HittableList) {
+    let mut world = empty_cornell();
+
+    let white = Arc::new(Material::Lambertain(Lambertain::new(Color::new(
+        0.73, 0.73, 0.73,
+    ))));
+
+    let spin = Track::new(Interpolation::Linear)
+        .with_key(
+            0.0,
+            glam::DMat4::from_scale_rotation_translation(
+                glam::DVec3::ONE * 40.0,
+                glam::DQuat::IDENTITY,
+                glam::DVec3::new(278.0, 0.0, 278.0),
+            ),
+        )
+        .with_key(
+            4.0,
+            glam::DMat4::from_scale_rotation_translation(
+                glam::DVec3::ONE * 40.0,
+                glam::DQuat::from_rotation_y(PI),
+                glam::DVec3::new(278.0, 100.0, 278.0),
+            ),
+        );
+
+    let camera = CameraTrack {
+        lookfrom: Track::new(Interpolation::Spline)
+            .with_key(0.0, Vec3::new(278.0, 278.0, -800.0))
+            .with_key(2.0, Vec3::new(150.0, 350.0, -600.0))
+            .with_key(4.0, Vec3::new(278.0, 278.0, -500.0)),
+        lookat: Track::new(Interpolation::Linear)
+            .with_key(0.0, Vec3::new(278.0, 278.0, 0.0))
+            .with_key(4.0, Vec3::new(278.0, 150.0, 278.0)),
+        vfov: Track::new(Interpolation::Linear).with_key(0.0, 40.0),
+    };
+
+    // 180 degree shutter at 24fps, spread over the camera's (0, 1) ray times
+    let shutter = 1.0 / 48.0;
+
+    let mesh = Arc::new(Mesh::new("data/teapot.obj".into()));
+    let teapot = Object::TriangleMesh(TriangleMesh::new(mesh, white));
+    world.add(Arc::new(Object::MotionTransform(MotionTransform::new(
+        spin.sample(time),
+        spin.sample(time + shutter),
+        (0.0, 1.0),
+        Arc::new(teapot),
+    ))));
+
+    let mut cfg = SceneConfig {
+        aspect_ratio: 1.0,
+        samples_per_pixel: 200,
+        background: Color::new_empty(),
+        ..Default::default()
+    };
+    camera.apply(&mut cfg, time);
+
+    (cfg, world)
+}
+
 fn dragon_cornell() -> HittableList {
     let mut world = empty_cornell();
 
@@ -193,37 +393,29 @@ fn dragon_cornell() -> HittableList {
         0.4,
     )));
 
-    let big_mat = glam::DMat4::from_scale_rotation_translation(
-        glam::DVec3::ONE * 55.0,
-        glam::DQuat::from_rotation_y(195_f64.to_radians()),
-        glam::DVec3 {
-            x: 275.0,
-            y: 0.0,
-            z: 400.0,
-        },
-    );
-
-    let glass_mat = glam::DMat4::from_scale_rotation_translation(
-        glam::DVec3::ONE * 30.0,
-        glam::DQuat::from_rotation_y(150_f64.to_radians()),
-        glam::DVec3 {
-            x: 150.0,
-            y: 0.0,
-            z: 200.0,
-        },
-    );
-
-    let alum_mat = glam::DMat4::from_scale_rotation_translation(
-        glam::DVec3::ONE * 30.0,
-        glam::DQuat::from_rotation_y(210_f64.to_radians()),
-        glam::DVec3 {
-            x: 400.0,
-            y: 0.0,
-            z: 200.0,
-        },
-    );
-
-    // let big_mat = glam::DMat4::IDENTITY;
+    // all three stood on the floor, the big one at the back
+    let big_place = Placement {
+        rotation: 195.0,
+        fit: Some(Vec3::new(400.0, 300.0, 400.0)),
+        position: Point::new(275.0, 0.0, 400.0),
+        on_ground: true,
+        ..Default::default()
+    };
+    let small = Vec3::new(220.0, 220.0, 220.0);
+    let glass_place = Placement {
+        rotation: 150.0,
+        fit: Some(small),
+        position: Point::new(150.0, 0.0, 200.0),
+        on_ground: true,
+        ..Default::default()
+    };
+    let alum_place = Placement {
+        rotation: 210.0,
+        fit: Some(small),
+        position: Point::new(400.0, 0.0, 200.0),
+        on_ground: true,
+        ..Default::default()
+    };
 
     let mesh = Arc::new(Mesh::new("data/dragon.obj".into()));
 
@@ -231,9 +423,10 @@ fn dragon_cornell() -> HittableList {
     let glass_dragon = Object::TriangleMesh(TriangleMesh::new(mesh.clone(), glass.clone()));
     let alum_dragon = Object::TriangleMesh(TriangleMesh::new(mesh.clone(), aluminum.clone()));
 
-    let big_scale = Object::MatTransform(MatTransform::new(big_mat, Arc::new(big_dragon)));
-    let glass_scale = Object::MatTransform(MatTransform::new(glass_mat, Arc::new(glass_dragon)));
-    let alum_scale = Object::MatTransform(MatTransform::new(alum_mat, Arc::new(alum_dragon)));
+    let big_scale = Object::MatTransform(MatTransform::placed(Arc::new(big_dragon), &big_place));
+    let glass_scale =
+        Object::MatTransform(MatTransform::placed(Arc::new(glass_dragon), &glass_place));
+    let alum_scale = Object::MatTransform(MatTransform::placed(Arc::new(alum_dragon), &alum_place));
 
     world.add(Arc::new(big_scale));
     world.add(Arc::new(glass_scale));
@@ -301,6 +494,263 @@ fn empty_cornell() -> HittableList {
     world
 }
 
+fn sdf_cornell() -> HittableList {
+    let mut world = empty_cornell();
+
+    let white = Arc::new(Material::Lambertain(Lambertain::new(Color::new(
+        0.73, 0.73, 0.73,
+    ))));
+    let glass = Arc::new(Material::Dielectric(Dielectric::new(1.5)));
+    let gold = Arc::new(Material::Metal(Metal::new(Color::new(0.8, 0.6, 0.2), 0.2)));
+
+    let twisted = SDF::RoundBox(Vec3::new(70.0, 150.0, 70.0), 15.0)
+        .twist(0.008)
+        .translate(Vec3::new(380.0, 150.0, 380.0));
+    world.add(Arc::new(Object::SDF(
+        SDFObject::new(
+            twisted,
+            AABB::new(
+                Point::new(280.0, 0.0, 280.0),
+                Point::new(480.0, 300.0, 480.0),
+            ),
+            white,
+        )
+        .with_step_scale(0.6),
+    )));
+
+    let blob = SDF::Sphere(60.0)
+        .translate(Vec3::new(400.0, 60.0, 150.0))
+        .smooth_union(
+            SDF::Sphere(40.0).translate(Vec3::new(340.0, 110.0, 130.0)),
+            30.0,
+        );
+    world.add(Arc::new(Object::SDF(
+        SDFObject::new(
+            blob,
+            AABB::new(
+                Point::new(290.0, 0.0, 80.0),
+                Point::new(470.0, 160.0, 220.0),
+            ),
+            glass,
+        )
+        .with_step_scale(0.8),
+    )));
+
+    let bulb = Arc::new(Object::SDF(
+        SDFObject::new(
+            SDF::Mandelbulb(8.0, 10),
+            AABB::new(Point::new(-1.2, -1.2, -1.2), Point::new(1.2, 1.2, 1.2)),
+            gold,
+        )
+        .with_step_scale(0.5),
+    ));
+    let bulb_mat = glam::DMat4::from_scale_rotation_translation(
+        glam::DVec3::ONE * 90.0,
+        glam::DQuat::from_rotation_x(-PI / 2.0),
+        glam::DVec3::new(150.0, 100.0, 200.0),
+    );
+    world.add(Arc::new(Object::MatTransform(MatTransform::new(
+        bulb_mat, bulb,
+    ))));
+
+    world
+}
+
+fn csg_cornell() -> HittableList {
+    let mut world = empty_cornell();
+
+    let white = Arc::new(Material::Lambertain(Lambertain::new(Color::new(
+        0.73, 0.73, 0.73,
+    ))));
+    let red = Arc::new(Material::Lambertain(Lambertain::new(Color::new(
+        0.65, 0.05, 0.05,
+    ))));
+    let glass = Arc::new(Material::Dielectric(Dielectric::new(1.5)));
+    let gold = Arc::new(Material::Metal(Metal::new(Color::new(0.8, 0.6, 0.2), 0.2)));
+
+    // box with a ball scooped out of its top corner, showing the box's material inside
+    let cube = Arc::new(Object::RectBox(RectBox::new(
+        &Point::new(0.0, 0.0, 0.0),
+        &Point::new(165.0, 165.0, 165.0),
+        white.clone(),
+    )));
+    let scoop = Arc::new(Object::Sphere(Sphere::new(
+        Point::new(165.0, 165.0, 0.0),
+        100.0,
+        red,
+    )));
+    let carved = Arc::new(Object::Csg(Csg::new(CsgOp::Difference, cube, scoop)));
+    let carved_mat = glam::DMat4::from_rotation_translation(
+        glam::DQuat::from_rotation_y(-20.0_f64.to_radians()),
+        glam::DVec3::new(300.0, 0.0, 250.0),
+    );
+    world.add(Arc::new(Object::MatTransform(MatTransform::new(
+        carved_mat, carved,
+    ))));
+
+    // biconvex lens where two spheres overlap
+    let lens = Csg::new(
+        CsgOp::Intersection,
+        Arc::new(Object::Sphere(Sphere::new(
+            Point::new(70.0, 260.0, 200.0),
+            100.0,
+            glass.clone(),
+        ))),
+        Arc::new(Object::Sphere(Sphere::new(
+            Point::new(170.0, 260.0, 200.0),
+            100.0,
+            glass,
+        ))),
+    );
+    world.add(Arc::new(Object::Csg(lens)));
+
+    // two balls joined into one shell, with a slab cut through the middle
+    let pair = Arc::new(Object::Csg(Csg::new(
+        CsgOp::Union,
+        Arc::new(Object::Sphere(Sphere::new(
+            Point::new(390.0, 50.0, 120.0),
+            50.0,
+            gold.clone(),
+        ))),
+        Arc::new(Object::Sphere(Sphere::new(
+            Point::new(460.0, 45.0, 160.0),
+            45.0,
+            gold,
+        ))),
+    )));
+    let slab = Arc::new(Object::RectBox(RectBox::new(
+        &Point::new(320.0, 40.0, 50.0),
+        &Point::new(530.0, 55.0, 230.0),
+        white,
+    )));
+    world.add(Arc::new(Object::Csg(Csg::new(
+        CsgOp::Difference,
+        pair,
+        slab,
+    ))));
+
+    world
+}
+
+fn fur_cornell() -> HittableList {
+    let mut world = empty_cornell();
+
+    let fur = Arc::new(Material::Lambertain(Lambertain::new(Color::new(
+        0.75, 0.45, 0.2,
+    ))));
+    let centre = Point::new(278.0, 160.0, 278.0);
+    let radius = 90.0;
+    world.add(Arc::new(Object::Sphere(Sphere::new(
+        centre,
+        radius,
+        fur.clone(),
+    ))));
+
+    // hairs stick out of the ball and droop under their own weight towards the tips
+    let mut hairs = HittableList::new();
+    for _ in 0..30000 {
+        let n = Vec3::random_unit_vector();
+        let length = random_double(30.0, 45.0);
+        let droop = Vec3::new(0.0, -0.4 * length, 0.0);
+        let root = centre + radius * n;
+        let cp = [
+            root,
+            root + length / 3.0 * n,
+            root + 2.0 * length / 3.0 * n + 0.4 * droop,
+            root + length * n + droop,
+        ];
+        hairs.add(Arc::new(Object::Curve(
+            Curve::new(cp, (1.2, 0.1), fur.clone()).with_mode(CurveMode::Cylinder),
+        )));
+    }
+    world.add(Arc::new(Object::BVHNode(BVHNode::new(hairs, (0.0, 0.0)))));
+
+    world
+}
+
+fn terrain() -> HittableList {
+    let mut world = HittableList::new();
+
+    // a few octaves of ridges and valleys
+    let size = 257;
+    let mut heights = Vec::with_capacity(size * size);
+    for z in 0..size {
+        for x in 0..size {
+            let (x, z) = (x as f64 / size as f64, z as f64 / size as f64);
+            let mut h = 0.0;
+            let mut scale = 1.0;
+            for octave in 0..5 {
+                let f = 8.0 * 2.0_f64.powi(octave);
+                h += scale * (f * x + 1.3 * octave as f64).sin() * (f * z * 1.1 + 0.7).cos();
+                scale *= 0.45;
+            }
+            heights.push(0.5 + 0.3 * h);
+        }
+    }
+
+    let ground = Arc::new(Material::Lambertain(Lambertain::new(Color::new(
+        0.45, 0.5, 0.3,
+    ))));
+    world.add(Arc::new(Object::Heightfield(Heightfield::from_heights(
+        heights,
+        size,
+        size,
+        Point::new(-10.0, 0.0, -10.0),
+        Vec3::new(20.0, 4.0, 20.0),
+        ground,
+    ))));
+
+    let sun = Arc::new(Material::DiffuseLight(DiffuseLight::from_color(
+        &Color::new(6.0, 5.5, 5.0),
+    )));
+    world.add(Arc::new(Object::Sphere(Sphere::new(
+        Point::new(40.0, 50.0, -30.0),
+        15.0,
+        sun,
+    ))));
+
+    world
+}
+
+// the same globe twice, bump mapped by its own brightness on the right
+fn bumpy_earth() -> HittableList {
+    let mut world = HittableList::new();
+
+    let earth_texture = Arc::new(Texture::ImageTexture(ImageTexture::new(
+        "earthmap.png".to_string(),
+    )));
+    let earth_surface = Arc::new(Material::Lambertain(Lambertain::from_texture(
+        earth_texture.clone(),
+    )));
+    let bumpy_surface = Arc::new(Material::Bumped(Bumped::bump(
+        earth_surface.clone(),
+        earth_texture,
+        0.5,
+    )));
+
+    world.add(Arc::new(Object::Sphere(Sphere::new(
+        Point::new(2.3, 0.0, 0.0),
+        2.0,
+        earth_surface,
+    ))));
+    world.add(Arc::new(Object::Sphere(Sphere::new(
+        Point::new(-2.3, 0.0, 0.0),
+        2.0,
+        bumpy_surface,
+    ))));
+
+    let sun = Arc::new(Material::DiffuseLight(DiffuseLight::from_color(
+        &Color::new(8.0, 7.5, 7.0),
+    )));
+    world.add(Arc::new(Object::Sphere(Sphere::new(
+        Point::new(-30.0, 10.0, -20.0),
+        5.0,
+        sun,
+    ))));
+
+    world
+}
+
 fn cornell_box() -> HittableList {
     let mut world = empty_cornell();
 
@@ -363,25 +813,62 @@ fn cornell_box() -> HittableList {
     //     z: 0.0,
     // });
 
-    let mesh_mat = glam::DMat4::from_scale_rotation_translation(
-        glam::DVec3::ONE * 25.0,
-        glam::DQuat::from_rotation_y(105_f64.to_radians()),
-        glam::DVec3 {
-            x: 300.0,
-            y: 50.0,
-            z: 100.0,
-        },
-    );
+    let mesh_place = Placement {
+        rotation: 105.0,
+        fit: Some(Vec3::new(f64::INFINITY, 75.0, f64::INFINITY)),
+        position: Point::new(300.0, 0.0, 100.0),
+        on_ground: true,
+        ..Default::default()
+    };
 
     let mesh = Arc::new(Mesh::new("data/teapot.obj".into()));
 
     let teapot = Object::TriangleMesh(TriangleMesh::new(mesh, white.clone()));
-    let mesh_scale = Object::MatTransform(MatTransform::new(mesh_mat, Arc::new(teapot)));
+    let mesh_scale = Object::MatTransform(MatTransform::placed(Arc::new(teapot), &mesh_place));
     world.add(Arc::new(mesh_scale));
 
     world
 }
 
+// the same teapot as loaded, subdivided twice, and subdivided twice keeping its sharper
+// edges, from left to right
+fn subdivided_cornell() -> HittableList {
+    let mut world = empty_cornell();
+
+    let white = Arc::new(Material::Lambertain(Lambertain::new(Color::new(
+        0.73, 0.73, 0.73,
+    ))));
+
+    let options = [
+        MeshOptions::default(),
+        MeshOptions {
+            subdivisions: 2,
+            ..Default::default()
+        },
+        MeshOptions {
+            subdivisions: 2,
+            crease_angle: Some(60.0),
+            ..Default::default()
+        },
+    ];
+    for (options, x) in options.into_iter().zip([440.0, 278.0, 116.0]) {
+        let mesh = Arc::new(Mesh::with_options("data/teapot.obj".into(), options));
+        let teapot = Arc::new(Object::TriangleMesh(TriangleMesh::new(mesh, white.clone())));
+        let place = Placement {
+            rotation: 105.0,
+            fit: Some(Vec3::new(140.0, f64::INFINITY, 140.0)),
+            position: Point::new(x, 0.0, 200.0),
+            on_ground: true,
+            ..Default::default()
+        };
+        world.add(Arc::new(Object::MatTransform(MatTransform::placed(
+            teapot, &place,
+        ))));
+    }
+
+    world
+}
+
 fn simple_light() -> HittableList {
     let mut world = HittableList::new();
 
@@ -0,0 +1,314 @@
+use std::{f64::consts::PI, sync::Arc};
+
+use crate::{
+    aabb::AABB,
+    hittable::{HitRecord, Hittable},
+    material::Material,
+    ray::Ray,
+    utils::clamp,
+    vec3::{Point, Vec3},
+};
+
+const MAX_STEPS: u32 = 512;
+// how close to the surface counts as a hit, and the offset for the finite difference normals
+const EPSILON: f64 = 1e-4;
+
+// signed distance functions, negative inside. shapes are centred on the origin
+pub enum SDF {
+    Sphere(f64),
+    // half extents
+    Box(Vec3),
+    // half extents, with the edges rounded off by the radius
+    RoundBox(Vec3, f64),
+    // major and minor radius, lying in the xz plane
+    Torus(f64, f64),
+    // power and iterations of the distance estimator, fits inside a radius of about 1.2
+    Mandelbulb(f64, u32),
+
+    Translate(Arc<SDF>, Vec3),
+    Union(Arc<SDF>, Arc<SDF>),
+    // blends the two over roughly the given distance
+    SmoothUnion(Arc<SDF>, Arc<SDF>, f64),
+    // the first with the second cut out of it
+    Subtraction(Arc<SDF>, Arc<SDF>),
+    Intersection(Arc<SDF>, Arc<SDF>),
+    // infinite copies spaced by the period along each axis, 0 to not repeat along that axis
+    Repeat(Arc<SDF>, Vec3),
+    // rotation around the y axis in radians per unit of height
+    Twist(Arc<SDF>, f64),
+}
+
+impl SDF {
+    pub fn translate(self, offset: Vec3) -> SDF {
+        SDF::Translate(Arc::new(self), offset)
+    }
+
+    pub fn union(self, other: SDF) -> SDF {
+        SDF::Union(Arc::new(self), Arc::new(other))
+    }
+
+    pub fn smooth_union(self, other: SDF, k: f64) -> SDF {
+        SDF::SmoothUnion(Arc::new(self), Arc::new(other), k)
+    }
+
+    pub fn subtract(self, other: SDF) -> SDF {
+        SDF::Subtraction(Arc::new(self), Arc::new(other))
+    }
+
+    pub fn intersect(self, other: SDF) -> SDF {
+        SDF::Intersection(Arc::new(self), Arc::new(other))
+    }
+
+    pub fn repeat(self, period: Vec3) -> SDF {
+        SDF::Repeat(Arc::new(self), period)
+    }
+
+    pub fn twist(self, rate: f64) -> SDF {
+        SDF::Twist(Arc::new(self), rate)
+    }
+
+    pub fn distance(&self, p: Point) -> f64 {
+        match self {
+            SDF::Sphere(radius) => p.length() - radius,
+            SDF::Box(b) => box_distance(p, *b),
+            SDF::RoundBox(b, radius) => {
+                box_distance(p, *b - Vec3::new(*radius, *radius, *radius)) - radius
+            }
+            SDF::Torus(major, minor) => {
+                let ring = (p.x * p.x + p.z * p.z).sqrt() - major;
+                (ring * ring + p.y * p.y).sqrt() - minor
+            }
+            SDF::Mandelbulb(power, iterations) => mandelbulb_distance(p, *power, *iterations),
+
+            SDF::Translate(a, offset) => a.distance(p - *offset),
+            SDF::Union(a, b) => a.distance(p).min(b.distance(p)),
+            SDF::SmoothUnion(a, b, k) => {
+                let (da, db) = (a.distance(p), b.distance(p));
+                let h = clamp(0.5 + 0.5 * (db - da) / k, 0.0, 1.0);
+                db + (da - db) * h - k * h * (1.0 - h)
+            }
+            SDF::Subtraction(a, b) => a.distance(p).max(-b.distance(p)),
+            SDF::Intersection(a, b) => a.distance(p).max(b.distance(p)),
+            SDF::Repeat(a, period) => {
+                let wrap = |x: f64, period: f64| {
+                    if period == 0.0 {
+                        x
+                    } else {
+                        x - period * (x / period).round()
+                    }
+                };
+                a.distance(Point::new(
+                    wrap(p.x, period.x),
+                    wrap(p.y, period.y),
+                    wrap(p.z, period.z),
+                ))
+            }
+            SDF::Twist(a, rate) => {
+                let (sin, cos) = (rate * p.y).sin_cos();
+                a.distance(Point::new(
+                    cos * p.x - sin * p.z,
+                    p.y,
+                    sin * p.x + cos * p.z,
+                ))
+            }
+        }
+    }
+
+    pub fn normal(&self, p: Point) -> Vec3 {
+        let dx = Vec3::new(EPSILON, 0.0, 0.0);
+        let dy = Vec3::new(0.0, EPSILON, 0.0);
+        let dz = Vec3::new(0.0, 0.0, EPSILON);
+        Vec3::new(
+            self.distance(p + dx) - self.distance(p - dx),
+            self.distance(p + dy) - self.distance(p - dy),
+            self.distance(p + dz) - self.distance(p - dz),
+        )
+        .unit()
+    }
+}
+
+fn box_distance(p: Point, b: Vec3) -> f64 {
+    let q = Vec3::new(p.x.abs() - b.x, p.y.abs() - b.y, p.z.abs() - b.z);
+    let outside = Vec3::new(q.x.max(0.0), q.y.max(0.0), q.z.max(0.0)).length();
+    let inside = q.x.max(q.y).max(q.z).min(0.0);
+    outside + inside
+}
+
+fn mandelbulb_distance(p: Point, power: f64, iterations: u32) -> f64 {
+    let mut z = p;
+    let mut dr = 1.0;
+    let mut r = z.length();
+
+    for _ in 0..iterations {
+        if !(1e-12..=2.0).contains(&r) {
+            break;
+        }
+
+        let theta = (z.z / r).acos() * power;
+        let phi = z.y.atan2(z.x) * power;
+        dr = r.powf(power - 1.0) * power * dr + 1.0;
+
+        let dir = Vec3::new(
+            theta.sin() * phi.cos(),
+            theta.sin() * phi.sin(),
+            theta.cos(),
+        );
+        z = r.powf(power) * dir + p;
+        r = z.length();
+    }
+
+    if r < 1e-12 {
+        return 0.0;
+    }
+    0.5 * r.ln() * r / dr
+}
+
+// an sdf sphere traced inside the given bounds, which should hold all of the surface you want to
+// see. step_scale under 1 takes smaller steps for sdfs that overestimate their distance, like
+// twists and smooth unions
+pub struct SDFObject {
+    sdf: SDF,
+    bounds: AABB,
+    step_scale: f64,
+    pub mat: Arc<Material>,
+}
+
+impl SDFObject {
+    pub fn new(sdf: SDF, bounds: AABB, mat: Arc<Material>) -> Self {
+        Self {
+            sdf,
+            bounds,
+            step_scale: 1.0,
+            mat,
+        }
+    }
+
+    pub fn with_step_scale(mut self, step_scale: f64) -> Self {
+        self.step_scale = step_scale;
+        self
+    }
+}
+
+impl Hittable for SDFObject {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let (mut t, t_end) = self.bounds.hit_interval(r, t_min, t_max)?;
+        let len = r.dir.length();
+
+        // t_min can be well under EPSILON once the object is scaled up, so a ray leaving the
+        // surface only counts hits after it has got clear of it. rays from anywhere else
+        // take the first hit, even where the bounds start right on the surface
+        let mut clear = self.sdf.distance(r.orig).abs() >= EPSILON;
+        for _ in 0..MAX_STEPS {
+            let dist = self.sdf.distance(r.at(t)).abs();
+            if dist >= EPSILON {
+                clear = true;
+            } else if clear {
+                let p = r.at(t);
+                let outward_normal = self.sdf.normal(p);
+                let theta = (-outward_normal.y).acos();
+                let phi = (-outward_normal.z).atan2(outward_normal.x) + PI;
+
+                let mut rec = HitRecord {
+                    t,
+                    p,
+                    uv: (phi / (2.0 * PI), theta / PI),
+                    mat: self.mat.clone(),
+                    ..Default::default()
+                };
+                rec.set_face_normal(r, outward_normal);
+                return Some(rec);
+            }
+
+            t += dist.max(EPSILON) * self.step_scale / len;
+            if t > t_end {
+                return None;
+            }
+        }
+        None
+    }
+
+    fn bounding_box(&self, _time: (f64, f64)) -> Option<AABB> {
+        Some(self.bounds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{SDFObject, SDF};
+    use crate::{
+        aabb::AABB,
+        hittable::{Hittable, MatTransform},
+        material::{Lambertain, Material},
+        object::Object,
+        ray::Ray,
+        sphere::Sphere,
+        vec3::{Point, Vec3},
+    };
+
+    #[test]
+    fn sphere_sdf_agrees_with_sphere() {
+        let mat = Arc::new(Material::Lambertain(Lambertain::default()));
+        let centre = Point::new(1.0, 2.0, -1.0);
+        let sphere = Sphere::new(centre, 1.5, mat.clone());
+        let pad = Vec3::new(1.5, 1.5, 1.5);
+        let sdf = SDFObject::new(
+            SDF::Sphere(1.5).translate(centre),
+            AABB::new(centre - pad, centre + pad),
+            mat,
+        );
+
+        let rays = [
+            Ray::new(Point::new(1.0, 2.0, 5.0), Vec3::new(0.0, 0.0, -1.0)),
+            Ray::new(Point::new(-4.0, 0.0, 0.0), Vec3::new(1.0, 0.4, -0.2)),
+            Ray::new(Point::new(3.0, 5.0, 1.0), Vec3::new(-2.0, -3.0, -1.5)),
+            // from inside, and one that misses
+            Ray::new(centre, Vec3::new(0.3, -1.0, 0.2)),
+            Ray::new(Point::new(-4.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)),
+        ];
+        for r in rays {
+            let expected = sphere.hit(&r, 0.001, f64::INFINITY);
+            let rec = sdf.hit(&r, 0.001, f64::INFINITY);
+            assert_eq!(rec.is_some(), expected.is_some());
+            if let (Some(rec), Some(expected)) = (rec, expected) {
+                assert!((rec.p - expected.p).length() < 1e-3);
+                assert!((rec.normal - expected.normal).length() < 1e-3);
+                assert_eq!(rec.front_face, expected.front_face);
+            }
+        }
+    }
+
+    #[test]
+    fn rays_leaving_the_surface_go_clear() {
+        let mat = Arc::new(Material::Lambertain(Lambertain::default()));
+        let pad = Vec3::new(1.0, 1.0, 1.0);
+        let sdf = SDFObject::new(SDF::Sphere(1.0), AABB::new(-pad, pad), mat);
+
+        let down = Ray::new(Point::new(0.3, 5.0, 0.1), Vec3::new(0.0, -1.0, 0.0));
+        let rec = sdf.hit(&down, 0.001, f64::INFINITY).unwrap();
+
+        // bounced back out, and refracted on through to the far side
+        let out = Ray::new(rec.p, rec.normal + Vec3::new(0.2, 0.0, 0.0));
+        assert!(sdf.hit(&out, 0.001, f64::INFINITY).is_none());
+        let through = Ray::new(rec.p, down.dir);
+        let far = sdf.hit(&through, 0.001, f64::INFINITY).unwrap();
+        assert!((far.p - Point::new(0.3, -rec.p.y, 0.1)).length() < 1e-3);
+        assert!(!far.front_face);
+
+        // scaled up, t_min is much less than EPSILON in the sphere's own space
+        let big = MatTransform::new(
+            glam::DMat4::from_scale(glam::DVec3::splat(100.0)),
+            Arc::new(Object::SDF(sdf)),
+        );
+        let down = Ray::new(Point::new(30.0, 500.0, 10.0), Vec3::new(0.0, -1.0, 0.0));
+        let rec = big.hit(&down, 0.001, f64::INFINITY).unwrap();
+        let out = Ray::new(rec.p, rec.normal + Vec3::new(0.2, 0.0, 0.0));
+        assert!(big.hit(&out, 0.001, f64::INFINITY).is_none());
+
+        // the bounds touch the sphere here, so the march starts right on the surface
+        let touching = Ray::new(Point::new(0.0, 0.0, 500.0), Vec3::new(0.0, 0.0, -1.0));
+        let rec = big.hit(&touching, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.p - Point::new(0.0, 0.0, 100.0)).length() < 1e-3);
+    }
+}
@@ -26,12 +26,21 @@ impl Sphere {
         }
     }
 
-    fn get_sphere_uv(&self, p: &Point) -> (f64, f64) {
+    pub(crate) fn get_sphere_uv(&self, p: &Point) -> (f64, f64) {
         let theta = (-p.y).acos();
         let phi = (-p.z).atan2(p.x) + PI;
 
         (phi / (2.0 * PI), theta / PI)
     }
+
+    // derivatives of the point at unit normal n along the uvs from get_sphere_uv
+    pub(crate) fn get_sphere_dpduv(&self, n: &Vec3) -> (Vec3, Vec3) {
+        // distance from the poles' axis, kept off zero so the poles don't divide by it
+        let s = (1.0 - n.y * n.y).sqrt().max(1e-8);
+        let dpdu = 2.0 * PI * self.radius * Vec3::new(n.z, 0.0, -n.x);
+        let dpdv = PI * self.radius * Vec3::new(-n.x * n.y / s, s, -n.y * n.z / s);
+        (dpdu, dpdv)
+    }
 }
 
 impl Hittable for Sphere {
@@ -61,6 +70,7 @@ impl Hittable for Sphere {
         let outward_normal = (rec.p - self.center) / self.radius;
         rec.set_face_normal(r, outward_normal);
         rec.uv = self.get_sphere_uv(&outward_normal);
+        (rec.dpdu, rec.dpdv) = self.get_sphere_dpduv(&outward_normal);
         rec.mat = self.mat.clone();
 
         Some(rec)
@@ -174,3 +184,46 @@ impl Hittable for MovingSphere {
         Some(AABB::from_surrounding(box0, box1))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{f64::consts::PI, sync::Arc};
+
+    use super::Sphere;
+    use crate::{
+        material::{Lambertain, Material},
+        vec3::{Point, Vec3},
+    };
+
+    #[test]
+    fn uv_derivatives() {
+        let sphere = Sphere::new(
+            Point::new(1.0, 2.0, 3.0),
+            2.5,
+            Arc::new(Material::Lambertain(Lambertain::default())),
+        );
+        // the point at (u, v), undoing get_sphere_uv
+        let at = |u: f64, v: f64| {
+            let (theta, phi) = (v * PI, u * 2.0 * PI);
+            let n = Vec3::new(
+                -theta.sin() * phi.cos(),
+                -theta.cos(),
+                theta.sin() * phi.sin(),
+            );
+            sphere.center + sphere.radius * n
+        };
+
+        let e = 1e-6;
+        for (u, v) in [(0.1, 0.3), (0.45, 0.5), (0.8, 0.9), (0.6, 0.02)] {
+            let n = (at(u, v) - sphere.center) / sphere.radius;
+            let (su, sv) = sphere.get_sphere_uv(&n);
+            assert!((su - u).abs() < 1e-9 && (sv - v).abs() < 1e-9);
+
+            let (dpdu, dpdv) = sphere.get_sphere_dpduv(&n);
+            let du = (at(u + e, v) - at(u - e, v)) / (2.0 * e);
+            let dv = (at(u, v + e) - at(u, v - e)) / (2.0 * e);
+            assert!((dpdu - du).length() < 1e-5);
+            assert!((dpdv - dv).length() < 1e-5);
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use std::f64::consts::PI;
+
+use crate::{
+    camera::{Camera, Shutter},
+    ray::Ray,
+    vec3::{Point, Vec3},
+};
+
+// where each eye goes in the output image
+#[derive(Clone, Copy, Default)]
+pub enum StereoLayout {
+    // left eye on the left half
+    #[default]
+    SideBySide,
+    // left eye on the top half
+    TopBottom,
+}
+
+impl StereoLayout {
+    // aspect ratio of one eye's part of an image with the given aspect ratio
+    pub fn eye_aspect(&self, aspect_ratio: f64) -> f64 {
+        match self {
+            StereoLayout::SideBySide => aspect_ratio / 2.0,
+            StereoLayout::TopBottom => aspect_ratio * 2.0,
+        }
+    }
+
+    // which eye (s, t) falls in, true for the left, and where it is in that eye's image
+    pub fn split(&self, s: f64, t: f64) -> (bool, f64, f64) {
+        match self {
+            StereoLayout::SideBySide if s < 0.5 => (true, s * 2.0, t),
+            StereoLayout::SideBySide => (false, s * 2.0 - 1.0, t),
+            StereoLayout::TopBottom if t >= 0.5 => (true, s, t * 2.0 - 1.0),
+            StereoLayout::TopBottom => (false, s, t * 2.0),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum StereoProjection {
+    // a regular thin lens camera per eye
+    Perspective,
+    // omnidirectional stereo covering the given horizontal field of view in degrees. 360 for a
+    // full panorama, 180 for VR-180. always covers 180 degrees vertically
+    Equirectangular(f64),
+}
+
+#[derive(Clone, Copy)]
+pub struct StereoConfig {
+    // distance between the eyes in scene units
+    pub ipd: f64,
+    // distance at which the eyes converge, infinity for parallel eyes
+    pub convergence: f64,
+    pub layout: StereoLayout,
+    pub projection: StereoProjection,
+}
+
+impl Default for StereoConfig {
+    fn default() -> Self {
+        Self {
+            ipd: 0.064,
+            convergence: f64::INFINITY,
+            layout: StereoLayout::SideBySide,
+            projection: StereoProjection::Perspective,
+        }
+    }
+}
+
+pub struct StereoCamera {
+    left: Camera,
+    right: Camera,
+    config: StereoConfig,
+}
+
+impl StereoCamera {
+    // aspect_ratio is the whole output image, each eye gets its half of it
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        lookfrom: Point,
+        lookat: Point,
+        vup: Vec3,
+        vfov: f64,
+        aspect_ratio: f64,
+        aperture: f64,
+        focus_dist: f64,
+        config: StereoConfig,
+    ) -> Self {
+        let center = Camera::new(
+            lookfrom,
+            lookat,
+            vup,
+            vfov,
+            config.layout.eye_aspect(aspect_ratio),
+            aperture,
+            focus_dist,
+        );
+
+        // omnidirectional eyes move with the view direction, so their offset is applied per ray
+        let (mut left, mut right) = (center, center);
+        if let StereoProjection::Perspective = config.projection {
+            left.with_eye_offset(-config.ipd / 2.0, config.convergence);
+            right.with_eye_offset(config.ipd / 2.0, config.convergence);
+        }
+
+        Self {
+            left,
+            right,
+            config,
+        }
+    }
+
+    pub fn with_time(mut self, t0: f64, t1: f64) -> Self {
+        self.left = self.left.with_time(t0, t1);
+        self.right = self.right.with_time(t0, t1);
+        self
+    }
+
+    pub fn with_shutter(mut self, shutter: Shutter) -> Self {
+        self.left = self.left.with_shutter(shutter);
+        self.right = self.right.with_shutter(shutter);
+        self
+    }
+
+    pub fn get_ray(&self, s: f64, t: f64) -> Ray {
+        let (left, s, t) = self.config.layout.split(s, t);
+        let camera = if left { &self.left } else { &self.right };
+
+        match self.config.projection {
+            StereoProjection::Perspective => camera.get_ray(s, t),
+            StereoProjection::Equirectangular(fov) => {
+                let theta = (s - 0.5) * fov.to_radians();
+                let phi = (t - 0.5) * PI;
+                let offset = if left { -0.5 } else { 0.5 } * self.config.ipd;
+                camera.get_omni_ray(theta, phi, offset, self.config.convergence, t)
+            }
+        }
+    }
+}
@@ -0,0 +1,727 @@
+use std::{
+    collections::{HashMap, HashSet},
+    ops::{Add, Mul},
+};
+
+use glam::DVec2;
+
+use crate::{
+    obj::{FaceIdx, OBJ},
+    vec3::Vec3,
+};
+
+// polygons sharing vertices, the form a mesh is refined in before it is split into triangles
+#[derive(Clone, Default)]
+pub struct PolyMesh {
+    pub positions: Vec<Vec3>,
+    // 0-indexed into positions, counter clockwise
+    pub faces: Vec<Vec<usize>>,
+    // one per face corner, so seams in the uv layout don't have to line up with the vertices
+    pub uvs: Option<Vec<Vec<DVec2>>>,
+    // also one per face corner, so hard edges can have a different normal either side
+    pub normals: Option<Vec<Vec<Vec3>>>,
+    // per corner as well, though they come from the vertices and only part at new seams if
+    // the uvs or normals do
+    pub colors: Option<Vec<Vec<Vec3>>>,
+    // one per face, the usemtl group it came from. none means they're all 0
+    pub materials: Option<Vec<u32>>,
+    // edges kept sharp, stored with the smaller index first
+    pub creases: HashSet<(usize, usize)>,
+}
+
+fn edge_key(a: usize, b: usize) -> (usize, usize) {
+    (a.min(b), a.max(b))
+}
+
+fn average(points: impl Iterator<Item = Vec3>) -> Vec3 {
+    let mut sum = Vec3::new_empty();
+    let mut n = 0;
+    for p in points {
+        sum += p;
+        n += 1;
+    }
+    sum / n.max(1) as f64
+}
+
+// which faces use each edge, and the edges and faces around each vertex
+struct Topology {
+    edge_faces: HashMap<(usize, usize), Vec<usize>>,
+    vert_edges: Vec<Vec<(usize, usize)>>,
+    vert_faces: Vec<Vec<usize>>,
+}
+
+impl Topology {
+    fn new(mesh: &PolyMesh) -> Self {
+        let mut edge_faces: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+        let mut vert_faces = vec![vec![]; mesh.positions.len()];
+
+        for (f, face) in mesh.faces.iter().enumerate() {
+            for (i, &v) in face.iter().enumerate() {
+                let next = face[(i + 1) % face.len()];
+                edge_faces.entry(edge_key(v, next)).or_default().push(f);
+                vert_faces[v].push(f);
+            }
+        }
+
+        let mut vert_edges = vec![vec![]; mesh.positions.len()];
+        for &(a, b) in edge_faces.keys() {
+            vert_edges[a].push((a, b));
+            vert_edges[b].push((a, b));
+        }
+
+        Self {
+            edge_faces,
+            vert_edges,
+            vert_faces,
+        }
+    }
+
+    // open edges and edges shared by more than two faces can't be smoothed across either
+    fn is_sharp(&self, mesh: &PolyMesh, edge: (usize, usize)) -> bool {
+        self.edge_faces[&edge].len() != 2 || mesh.creases.contains(&edge)
+    }
+
+    fn sharp_edges(&self, mesh: &PolyMesh, v: usize) -> Vec<(usize, usize)> {
+        self.vert_edges[v]
+            .iter()
+            .copied()
+            .filter(|e| self.is_sharp(mesh, *e))
+            .collect()
+    }
+}
+
+fn other_end(edge: (usize, usize), v: usize) -> usize {
+    if edge.0 == v {
+        edge.1
+    } else {
+        edge.0
+    }
+}
+
+fn face_normal(mesh: &PolyMesh, face: &[usize]) -> Vec3 {
+    let p0 = mesh.positions[face[0]];
+    let mut n = Vec3::new_empty();
+    for i in 1..face.len() - 1 {
+        n += (mesh.positions[face[i]] - p0).cross(mesh.positions[face[i + 1]] - p0);
+    }
+    n
+}
+
+impl PolyMesh {
+    // uvs and normals are only kept if every face corner has them
+    pub fn from_obj(obj: &OBJ) -> Self {
+        let corners =
+            |has: &dyn Fn(&FaceIdx) -> bool| obj.faces.iter().all(|f| f.verts.iter().all(has));
+
+        Self {
+            positions: obj.vers.clone(),
+            faces: obj
+                .faces
+                .iter()
+                .map(|f| f.verts.iter().map(|v| v.vert_idx - 1).collect())
+                .collect(),
+            uvs: corners(&|v| v.uv_idx != 0).then(|| {
+                obj.faces
+                    .iter()
+                    .map(|f| f.verts.iter().map(|v| obj.uvs[v.uv_idx - 1]).collect())
+                    .collect()
+            }),
+            normals: corners(&|v| v.normal_idx != 0).then(|| {
+                obj.faces
+                    .iter()
+                    .map(|f| {
+                        f.verts
+                            .iter()
+                            .map(|v| obj.normals[v.normal_idx - 1])
+                            .collect()
+                    })
+                    .collect()
+            }),
+            colors: (!obj.colors.is_empty()).then(|| {
+                obj.faces
+                    .iter()
+                    .map(|f| f.verts.iter().map(|v| obj.colors[v.vert_idx - 1]).collect())
+                    .collect()
+            }),
+            materials: (!obj.materials.is_empty())
+                .then(|| obj.faces.iter().map(|f| f.material as u32).collect()),
+            creases: HashSet::new(),
+        }
+    }
+
+    // marks every edge where the faces either side meet at more than the angle, in degrees
+    pub fn crease_by_angle(&mut self, angle: f64) {
+        let topology = Topology::new(self);
+        let cos_limit = angle.to_radians().cos();
+
+        for (edge, faces) in &topology.edge_faces {
+            if let [f0, f1] = faces[..] {
+                let n0 = face_normal(self, &self.faces[f0]).unit();
+                let n1 = face_normal(self, &self.faces[f1]).unit();
+                if n0.dot(n1) < cos_limit {
+                    self.creases.insert(*edge);
+                }
+            }
+        }
+    }
+
+    // refines the mesh the given number of times. all triangle meshes use loop subdivision,
+    // anything with other polygons uses catmull-clark, which turns everything into quads
+    pub fn subdivide(&self, levels: u32) -> PolyMesh {
+        let mut mesh = self.clone();
+        for _ in 0..levels {
+            mesh = if mesh.faces.iter().all(|f| f.len() == 3) {
+                mesh.loop_step()
+            } else {
+                mesh.catmull_clark_step()
+            };
+        }
+        mesh
+    }
+
+    // vertex rule shared by both schemes: creases follow the curve through their two sharp
+    // neighbours, and corners where three or more creases meet stay where they are
+    fn crease_vertex(&self, topology: &Topology, v: usize) -> Option<Vec3> {
+        let sharp = topology.sharp_edges(self, v);
+        match sharp.len() {
+            0 | 1 => None,
+            2 => {
+                let a = self.positions[other_end(sharp[0], v)];
+                let b = self.positions[other_end(sharp[1], v)];
+                Some(0.75 * self.positions[v] + 0.125 * (a + b))
+            }
+            _ => Some(self.positions[v]),
+        }
+    }
+
+    fn loop_step(&self) -> PolyMesh {
+        let topology = Topology::new(self);
+        let mut positions = Vec::with_capacity(self.positions.len() + topology.edge_faces.len());
+
+        for v in 0..self.positions.len() {
+            let p = self.positions[v];
+            let n = topology.vert_edges[v].len();
+            let moved = match self.crease_vertex(&topology, v) {
+                Some(p) => p,
+                None if n == 0 => p,
+                None => {
+                    let beta = if n == 3 {
+                        3.0 / 16.0
+                    } else {
+                        3.0 / (8.0 * n as f64)
+                    };
+                    let neighbours = topology.vert_edges[v]
+                        .iter()
+                        .map(|e| self.positions[other_end(*e, v)]);
+                    (1.0 - n as f64 * beta) * p + beta * n as f64 * average(neighbours)
+                }
+            };
+            positions.push(moved);
+        }
+
+        let mut edge_points = HashMap::new();
+        for (&(a, b), faces) in &topology.edge_faces {
+            let (pa, pb) = (self.positions[a], self.positions[b]);
+            let p = if topology.is_sharp(self, (a, b)) {
+                0.5 * (pa + pb)
+            } else {
+                // the corners of the two triangles opposite the edge
+                let opposite = faces.iter().map(|f| {
+                    let c = *self.faces[*f]
+                        .iter()
+                        .find(|v| **v != a && **v != b)
+                        .unwrap();
+                    self.positions[c]
+                });
+                0.375 * (pa + pb) + 0.25 * average(opposite)
+            };
+            edge_points.insert((a, b), positions.len());
+            positions.push(p);
+        }
+        let e = |a: usize, b: usize| edge_points[&edge_key(a, b)];
+
+        let mut faces = Vec::with_capacity(self.faces.len() * 4);
+        for face in &self.faces {
+            let (a, b, c) = (face[0], face[1], face[2]);
+            let (ab, bc, ca) = (e(a, b), e(b, c), e(c, a));
+            faces.push(vec![a, ab, ca]);
+            faces.push(vec![b, bc, ab]);
+            faces.push(vec![c, ca, bc]);
+            faces.push(vec![ab, bc, ca]);
+        }
+
+        PolyMesh {
+            positions,
+            faces,
+            uvs: self.uvs.as_deref().map(loop_corners),
+            // the refined surface has its own shape, so the old normals don't fit it
+            normals: None,
+            colors: self.colors.as_deref().map(loop_corners),
+            materials: self.split_materials(|_| 4),
+            creases: self.split_creases(&edge_points),
+        }
+    }
+
+    fn catmull_clark_step(&self) -> PolyMesh {
+        let topology = Topology::new(self);
+        let face_points: Vec<Vec3> = self
+            .faces
+            .iter()
+            .map(|f| average(f.iter().map(|v| self.positions[*v])))
+            .collect();
+
+        let mut positions =
+            Vec::with_capacity(self.positions.len() + topology.edge_faces.len() + self.faces.len());
+
+        for v in 0..self.positions.len() {
+            let p = self.positions[v];
+            let n = topology.vert_edges[v].len();
+            let moved = match self.crease_vertex(&topology, v) {
+                Some(p) => p,
+                None if n == 0 => p,
+                None => {
+                    let f = average(topology.vert_faces[v].iter().map(|f| face_points[*f]));
+                    let r = average(
+                        topology.vert_edges[v]
+                            .iter()
+                            .map(|(a, b)| 0.5 * (self.positions[*a] + self.positions[*b])),
+                    );
+                    let n = n as f64;
+                    (f + 2.0 * r + (n - 3.0) * p) / n
+                }
+            };
+            positions.push(moved);
+        }
+
+        let mut edge_points = HashMap::new();
+        for (&(a, b), faces) in &topology.edge_faces {
+            let (pa, pb) = (self.positions[a], self.positions[b]);
+            let p = if topology.is_sharp(self, (a, b)) {
+                0.5 * (pa + pb)
+            } else {
+                0.5 * (0.5 * (pa + pb) + average(faces.iter().map(|f| face_points[*f])))
+            };
+            edge_points.insert((a, b), positions.len());
+            positions.push(p);
+        }
+        let e = |a: usize, b: usize| edge_points[&edge_key(a, b)];
+
+        let mut faces = vec![];
+        for (f, face) in self.faces.iter().enumerate() {
+            let centre = positions.len();
+            positions.push(face_points[f]);
+
+            let n = face.len();
+            for i in 0..n {
+                let (prev, v, next) = (face[(i + n - 1) % n], face[i], face[(i + 1) % n]);
+                faces.push(vec![v, e(v, next), centre, e(prev, v)]);
+            }
+        }
+
+        PolyMesh {
+            positions,
+            faces,
+            uvs: self.uvs.as_deref().map(quad_corners),
+            // the refined surface has its own shape, so the old normals don't fit it
+            normals: None,
+            colors: self.colors.as_deref().map(quad_corners),
+            materials: self.split_materials(|face| face.len()),
+            creases: self.split_creases(&edge_points),
+        }
+    }
+
+    // both halves of a creased edge stay creased
+    fn split_creases(
+        &self,
+        edge_points: &HashMap<(usize, usize), usize>,
+    ) -> HashSet<(usize, usize)> {
+        self.creases
+            .iter()
+            .filter_map(|&(a, b)| edge_points.get(&(a, b)).map(|&m| (a, b, m)))
+            .flat_map(|(a, b, m)| [edge_key(a, m), edge_key(m, b)])
+            .collect()
+    }
+
+    // each face's material, repeated for however many faces it's split into
+    fn split_materials(&self, pieces: impl Fn(&[usize]) -> usize) -> Option<Vec<u32>> {
+        self.materials.as_ref().map(|materials| {
+            self.faces
+                .iter()
+                .zip(materials)
+                .flat_map(|(face, m)| std::iter::repeat_n(*m, pieces(face)))
+                .collect()
+        })
+    }
+
+    // fans every polygon into triangles
+    pub fn triangulate(&self) -> PolyMesh {
+        PolyMesh {
+            positions: self.positions.clone(),
+            faces: fan(&self.faces),
+            uvs: self.uvs.as_deref().map(fan),
+            normals: self.normals.as_deref().map(fan),
+            colors: self.colors.as_deref().map(fan),
+            materials: self.split_materials(|face| face.len() - 2),
+            creases: self.creases.clone(),
+        }
+    }
+
+    // splits triangles until no edge is longer than max_edge. each pass halves every edge that
+    // is too long and splits the triangles around it to match, so neighbours never crack apart
+    pub fn tessellate(&self, max_edge: f64) -> PolyMesh {
+        let mut mesh = self.triangulate();
+
+        for _ in 0..32 {
+            let mut midpoints = HashMap::new();
+            for face in &mesh.faces {
+                for i in 0..3 {
+                    let (a, b) = (face[i], face[(i + 1) % 3]);
+                    let (pa, pb) = (mesh.positions[a], mesh.positions[b]);
+                    if (pa - pb).length() > max_edge && !midpoints.contains_key(&edge_key(a, b)) {
+                        midpoints.insert(edge_key(a, b), mesh.positions.len());
+                        mesh.positions.push(0.5 * (pa + pb));
+                    }
+                }
+            }
+            if midpoints.is_empty() {
+                break;
+            }
+
+            let mut faces = vec![];
+            let mut uvs = vec![];
+            let mut normals = vec![];
+            let mut colors = vec![];
+            let mut materials = vec![];
+            for (f, face) in mesh.faces.iter().enumerate() {
+                let mid = |i: usize| midpoints.get(&edge_key(face[i], face[(i + 1) % 3]));
+                let split: Vec<bool> = (0..3).map(|i| mid(i).is_some()).collect();
+
+                // turn the pattern so its split edges line up with the face's
+                let (pattern, r) = match split.iter().filter(|s| **s).count() {
+                    0 => (&[[0, 1, 2]][..], 0),
+                    1 => (SPLIT_ONE, split.iter().position(|s| *s).unwrap()),
+                    2 => (SPLIT_TWO, (split.iter().position(|s| !*s).unwrap() + 1) % 3),
+                    _ => (SPLIT_THREE, 0),
+                };
+                let turn = |l: usize| {
+                    if l < 3 {
+                        (l + r) % 3
+                    } else {
+                        3 + (l + r) % 3
+                    }
+                };
+                let vertex = |l: usize| {
+                    if l < 3 {
+                        face[l]
+                    } else {
+                        *mid(l - 3).unwrap()
+                    }
+                };
+
+                for tri in pattern {
+                    let tri = tri.map(turn);
+                    faces.push(tri.iter().map(|l| vertex(*l)).collect());
+                    if let Some(attr) = &mesh.uvs {
+                        uvs.push(tri.iter().map(|l| split_corner(&attr[f], *l)).collect());
+                    }
+                    if let Some(attr) = &mesh.normals {
+                        normals.push(tri.iter().map(|l| split_corner(&attr[f], *l)).collect());
+                    }
+                    if let Some(attr) = &mesh.colors {
+                        colors.push(tri.iter().map(|l| split_corner(&attr[f], *l)).collect());
+                    }
+                    if let Some(attr) = &mesh.materials {
+                        materials.push(attr[f]);
+                    }
+                }
+            }
+
+            mesh.creases = mesh.split_creases(&midpoints);
+            mesh.faces = faces;
+            mesh.uvs = mesh.uvs.map(|_| uvs);
+            mesh.normals = mesh.normals.map(|_| normals);
+            mesh.colors = mesh.colors.map(|_| colors);
+            mesh.materials = mesh.materials.map(|_| materials);
+        }
+        mesh
+    }
+
+    // area weighted normal at every corner. vertices at the same position count as one, so
+    // seams where the mesh has been cut apart still shade smoothly
+    pub fn smooth_normals(&self) -> Vec<Vec<Vec3>> {
+        let key = |p: Vec3| [p.x.to_bits(), p.y.to_bits(), p.z.to_bits()];
+        let mut sums: HashMap<[u64; 3], Vec3> = HashMap::new();
+        for face in &self.faces {
+            let n = face_normal(self, face);
+            for v in face {
+                *sums.entry(key(self.positions[*v])).or_default() += n;
+            }
+        }
+
+        self.faces
+            .iter()
+            .map(|face| {
+                face.iter()
+                    .map(|v| sums[&key(self.positions[*v])].unit())
+                    .collect()
+            })
+            .collect()
+    }
+
+    // angle weighted normal at every corner, averaged only with the faces around it that meet
+    // its own at no more than crease_angle degrees, so hard edges stay hard. vertices at the
+    // same position count as one, as in smooth_normals
+    pub fn creased_normals(&self, crease_angle: f64) -> Vec<Vec<Vec3>> {
+        let key = |p: Vec3| [p.x.to_bits(), p.y.to_bits(), p.z.to_bits()];
+        let cos_limit = crease_angle.to_radians().cos();
+        let face_normals: Vec<Vec3> = self
+            .faces
+            .iter()
+            .map(|face| {
+                let n = face_normal(self, face);
+                if n.length_squared() > 0.0 {
+                    n.unit()
+                } else {
+                    n
+                }
+            })
+            .collect();
+
+        // the faces around each position, with their normals weighted by the angle there
+        let mut around: HashMap<[u64; 3], Vec<(usize, Vec3)>> = HashMap::new();
+        for (f, face) in self.faces.iter().enumerate() {
+            let n = face.len();
+            for i in 0..n {
+                let p = self.positions[face[i]];
+                let e1 = self.positions[face[(i + 1) % n]] - p;
+                let e2 = self.positions[face[(i + n - 1) % n]] - p;
+                if e1.length_squared() == 0.0 || e2.length_squared() == 0.0 {
+                    continue;
+                }
+                let angle = e1.unit().dot(e2.unit()).clamp(-1.0, 1.0).acos();
+                around
+                    .entry(key(p))
+                    .or_default()
+                    .push((f, angle * face_normals[f]));
+            }
+        }
+
+        self.faces
+            .iter()
+            .enumerate()
+            .map(|(f, face)| {
+                let own = face_normals[f];
+                face.iter()
+                    .map(|v| {
+                        let sum = around
+                            .get(&key(self.positions[*v]))
+                            .into_iter()
+                            .flatten()
+                            .filter(|(g, _)| face_normals[*g].dot(own) >= cos_limit)
+                            .fold(Vec3::new_empty(), |sum, (_, n)| sum + *n);
+                        if sum.length_squared() > 0.0 {
+                            sum.unit()
+                        } else {
+                            own
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+// splits per corner values the same way triangulate splits the faces
+fn fan<T: Copy>(corners: &[Vec<T>]) -> Vec<Vec<T>> {
+    corners
+        .iter()
+        .flat_map(|c| (1..c.len() - 1).map(move |i| vec![c[0], c[i], c[i + 1]]))
+        .collect()
+}
+
+// per corner values for the four triangles loop_step splits each triangle into, halfway along
+// the edges
+fn loop_corners<T: Copy + Add<Output = T> + Mul<f64, Output = T>>(
+    corners: &[Vec<T>],
+) -> Vec<Vec<T>> {
+    corners
+        .iter()
+        .flat_map(|c| {
+            let (ab, bc, ca) = (
+                (c[0] + c[1]) * 0.5,
+                (c[1] + c[2]) * 0.5,
+                (c[2] + c[0]) * 0.5,
+            );
+            [
+                vec![c[0], ab, ca],
+                vec![c[1], bc, ab],
+                vec![c[2], ca, bc],
+                vec![ab, bc, ca],
+            ]
+        })
+        .collect()
+}
+
+// and for the quads catmull_clark_step makes around each polygon's centre
+fn quad_corners<T: Copy + Add<Output = T> + Mul<f64, Output = T>>(
+    corners: &[Vec<T>],
+) -> Vec<Vec<T>> {
+    corners
+        .iter()
+        .flat_map(|c| {
+            let n = c.len();
+            let centre = c[1..].iter().fold(c[0], |sum, v| sum + *v) * (1.0 / n as f64);
+            (0..n).map(move |i| {
+                let (prev, next) = (c[(i + n - 1) % n], c[(i + 1) % n]);
+                vec![c[i], (c[i] + next) * 0.5, centre, (prev + c[i]) * 0.5]
+            })
+        })
+        .collect()
+}
+
+// ways to split a triangle when some of its edges are halved. 0 to 2 are the corners and 3 to 5
+// the midpoints of the edges leaving them. one halves edge 0, two leaves edge 2 whole
+const SPLIT_ONE: &[[usize; 3]] = &[[0, 3, 2], [3, 1, 2]];
+const SPLIT_TWO: &[[usize; 3]] = &[[1, 4, 3], [0, 3, 4], [0, 4, 2]];
+const SPLIT_THREE: &[[usize; 3]] = &[[0, 3, 5], [1, 4, 3], [2, 5, 4], [3, 4, 5]];
+
+// a per corner attribute at one of the points of a split pattern
+fn split_corner<T: Copy + Add<Output = T> + Mul<f64, Output = T>>(attr: &[T], l: usize) -> T {
+    if l < 3 {
+        attr[l]
+    } else {
+        (attr[l - 3] + attr[(l - 2) % 3]) * 0.5
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PolyMesh;
+    use crate::vec3::Vec3;
+
+    fn cube() -> PolyMesh {
+        let positions = (0..8)
+            .map(|i| {
+                Vec3::new(
+                    (i & 1) as f64 * 2.0 - 1.0,
+                    (i >> 1 & 1) as f64 * 2.0 - 1.0,
+                    (i >> 2 & 1) as f64 * 2.0 - 1.0,
+                )
+            })
+            .collect();
+        let faces = vec![
+            vec![0, 2, 3, 1],
+            vec![4, 5, 7, 6],
+            vec![0, 1, 5, 4],
+            vec![2, 6, 7, 3],
+            vec![0, 4, 6, 2],
+            vec![1, 3, 7, 5],
+        ];
+        PolyMesh {
+            positions,
+            faces,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn catmull_clark_cube_shrinks_towards_sphere() {
+        let mesh = cube().subdivide(2);
+        assert_eq!(mesh.faces.len(), 6 * 16);
+
+        // corners pull in, face centres stay on the faces
+        let lengths: Vec<f64> = mesh.positions.iter().map(|p| p.length()).collect();
+        let max = lengths.iter().cloned().fold(0.0, f64::max);
+        assert!(max < 3.0_f64.sqrt() - 0.1);
+        assert!(lengths.iter().all(|l| *l > 0.8));
+    }
+
+    #[test]
+    fn fully_creased_cube_keeps_its_shape() {
+        let mut mesh = cube();
+        mesh.crease_by_angle(30.0);
+        assert_eq!(mesh.creases.len(), 12);
+
+        let mesh = mesh.subdivide(2);
+        for p in &mesh.positions {
+            // every point stays on the surface of the original cube
+            let max = p.x.abs().max(p.y.abs()).max(p.z.abs());
+            assert!((max - 1.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn creased_normals_split_at_the_angle() {
+        let mesh = cube();
+
+        // the faces of a cube meet at 90 degrees, so below that every corner keeps its face's
+        // normal and above it they all point out along the diagonals
+        let flat = mesh.creased_normals(60.0);
+        let smooth = mesh.creased_normals(100.0);
+        for (f, face) in mesh.faces.iter().enumerate() {
+            let own = flat[f][0];
+            assert!((own.length() - 1.0).abs() < 1e-12);
+            for (c, v) in face.iter().enumerate() {
+                assert!((flat[f][c] - own).length() < 1e-12);
+                let diagonal = mesh.positions[*v].unit();
+                assert!((smooth[f][c] - diagonal).length() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn loop_keeps_triangles_and_open_edges() {
+        // a single triangle is all boundary, so it just splits into four flat triangles
+        let mesh = PolyMesh {
+            positions: vec![
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(1.0, 0.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+            ],
+            faces: vec![vec![0, 1, 2]],
+            ..Default::default()
+        }
+        .subdivide(1);
+
+        assert_eq!(mesh.faces.len(), 4);
+        assert!(mesh.faces.iter().all(|f| f.len() == 3));
+        assert!(mesh.positions.iter().all(|p| p.z == 0.0));
+    }
+
+    #[test]
+    fn tessellated_halves_stay_joined() {
+        // a square split along its diagonal, one half much finer than the other would need
+        let mesh = PolyMesh {
+            positions: vec![
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(1.0, 0.0, 0.0),
+                Vec3::new(1.0, 1.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+            ],
+            faces: vec![vec![0, 1, 2], vec![0, 2, 3]],
+            ..Default::default()
+        }
+        .tessellate(0.3);
+
+        let mut edges = std::collections::HashMap::new();
+        for face in &mesh.faces {
+            for i in 0..3 {
+                let (a, b) = (face[i], face[(i + 1) % 3]);
+                assert!((mesh.positions[a] - mesh.positions[b]).length() <= 0.3);
+                *edges.entry((a.min(b), a.max(b))).or_insert(0) += 1;
+            }
+        }
+
+        // only the outside of the square is left with edges that have a face on one side
+        for ((a, b), count) in edges {
+            if count == 1 {
+                let (pa, pb) = (mesh.positions[a], mesh.positions[b]);
+                let on_side =
+                    |c: fn(&Vec3) -> f64| c(&pa) == c(&pb) && (c(&pa) == 0.0 || c(&pa) == 1.0);
+                assert!(on_side(|p| p.x) || on_side(|p| p.y));
+            } else {
+                assert_eq!(count, 2);
+            }
+        }
+    }
+}
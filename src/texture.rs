@@ -1,6 +1,7 @@
 use std::{path::Path, sync::Arc};
 
 use crate::{
+    hittable::HitRecord,
     utils::clamp,
     vec3::{Color, Point},
 };
@@ -9,6 +10,7 @@ pub enum Texture {
     SolidColor(SolidColor),
     CheckerTexture(CheckerTexture),
     ImageTexture(ImageTexture),
+    VertexColor(VertexColor),
 }
 
 impl TextureMat for Texture {
@@ -17,6 +19,17 @@ impl TextureMat for Texture {
             Texture::SolidColor(sc) => sc.value(u, v, p),
             Texture::CheckerTexture(ct) => ct.value(u, v, p),
             Texture::ImageTexture(img) => img.value(u, v, p),
+            Texture::VertexColor(vc) => vc.value(u, v, p),
+        }
+    }
+}
+
+impl Texture {
+    // the value at a hit, which is the only place a vertex colour can come from
+    pub fn value_at(&self, rec: &HitRecord) -> Color {
+        match self {
+            Texture::VertexColor(vc) => rec.color.unwrap_or(vc.fallback),
+            _ => self.value(rec.uv.0, rec.uv.1, &rec.p),
         }
     }
 }
@@ -135,3 +148,20 @@ impl TextureMat for ImageTexture {
         )
     }
 }
+
+// the colour interpolated from a mesh's vertices, or `fallback` on anything without them
+pub struct VertexColor {
+    pub fallback: Color,
+}
+
+impl VertexColor {
+    pub fn new(fallback: Color) -> Self {
+        Self { fallback }
+    }
+}
+
+impl TextureMat for VertexColor {
+    fn value(&self, _u: f64, _v: f64, _p: &Point) -> Color {
+        self.fallback
+    }
+}
@@ -0,0 +1,268 @@
+use std::{f64::consts::PI, sync::Arc};
+
+use crate::{
+    aabb::AABB,
+    hittable::{area_pdf_value, HitRecord, Hittable},
+    material::Material,
+    quadric::azimuth,
+    ray::Ray,
+    utils::{random_double_normal, solve_quadratic},
+    vec3::{Point, Vec3},
+};
+
+// ring lying in the xz plane around `center`. the tube of minor_radius is swept along a circle of
+// major_radius
+pub struct Torus {
+    center: Point,
+    major_radius: f64,
+    minor_radius: f64,
+    pub mat: Arc<Material>,
+}
+
+impl Torus {
+    pub fn new(center: Point, major_radius: f64, minor_radius: f64, mat: Arc<Material>) -> Self {
+        Self {
+            center,
+            major_radius,
+            minor_radius,
+            mat,
+        }
+    }
+
+    fn area(&self) -> f64 {
+        4.0 * PI * PI * self.major_radius * self.minor_radius
+    }
+}
+
+impl Hittable for Torus {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        // solving with a unit direction from the point on the ray closest to the centre keeps the
+        // coefficients well scaled however far away the ray starts
+        let len = r.dir.length();
+        let d = r.dir / len;
+        let shift = -(r.orig - self.center).dot(d);
+        let o = r.orig - self.center + shift * d;
+
+        let rr = self.major_radius * self.major_radius;
+        let g = o.dot(o) + rr - self.minor_radius * self.minor_radius;
+        let f = o.dot(d);
+
+        // (|p|^2 + R^2 - r^2)^2 = 4 R^2 (x^2 + z^2) along the ray
+        let roots = solve_quartic(
+            4.0 * f,
+            4.0 * f * f + 2.0 * g - 4.0 * rr + 4.0 * rr * d.y * d.y,
+            4.0 * f * g - 8.0 * rr * f + 8.0 * rr * o.y * d.y,
+            g * g - 4.0 * rr * o.dot(o) + 4.0 * rr * o.y * o.y,
+        );
+
+        let t = roots
+            .into_iter()
+            .map(|t| (t + shift) / len)
+            .find(|t| *t >= t_min && *t <= t_max)?;
+
+        let p = r.at(t) - self.center;
+        let ring = Vec3::new(p.x, 0.0, p.z).unit() * self.major_radius;
+        let outward_normal = (p - ring) / self.minor_radius;
+
+        let tube = (p.y).atan2(ring.dot(p - ring) / self.major_radius);
+        let v = (if tube < 0.0 { tube + 2.0 * PI } else { tube }) / (2.0 * PI);
+
+        let mut rec = HitRecord {
+            t,
+            p: r.at(t),
+            uv: (azimuth(p.x, p.z), v),
+            mat: self.mat.clone(),
+            ..Default::default()
+        };
+        rec.set_face_normal(r, outward_normal);
+        Some(rec)
+    }
+
+    fn bounding_box(&self, _time: (f64, f64)) -> Option<AABB> {
+        let outer = self.major_radius + self.minor_radius;
+        let extent = Vec3::new(outer, self.minor_radius, outer);
+        Some(AABB::new(self.center - extent, self.center + extent))
+    }
+
+    fn pdf_value(&self, o: &Point, v: &Point) -> f64 {
+        area_pdf_value(self, self.area(), o, v)
+    }
+
+    fn random(&self, o: &Vec3) -> Vec3 {
+        // the outside of the ring has more area than the inside, so reject tube angles in
+        // proportion to their distance from the axis
+        let (big, small) = (self.major_radius, self.minor_radius);
+        let theta = loop {
+            let theta = 2.0 * PI * random_double_normal();
+            if random_double_normal() * (big + small) <= big + small * theta.cos() {
+                break theta;
+            }
+        };
+        let phi = 2.0 * PI * random_double_normal();
+
+        let dist = big + small * theta.cos();
+        let p = Vec3::new(dist * phi.cos(), small * theta.sin(), dist * phi.sin());
+        self.center + p - *o
+    }
+}
+
+// largest real root of x^3 + a x^2 + b x + c
+fn largest_cubic_root(a: f64, b: f64, c: f64) -> f64 {
+    // x = y - a / 3 gives y^3 + p y + q
+    let p = b - a * a / 3.0;
+    let q = 2.0 * a * a * a / 27.0 - a * b / 3.0 + c;
+    let disc = q * q / 4.0 + p * p * p / 27.0;
+
+    let y = if disc > 0.0 {
+        let sqrt_disc = disc.sqrt();
+        (-q / 2.0 + sqrt_disc).cbrt() + (-q / 2.0 - sqrt_disc).cbrt()
+    } else {
+        // three real roots, the largest is at the smallest angle
+        let m = (-p / 3.0).sqrt();
+        let cos = if m > 0.0 {
+            (-q / (2.0 * m * m * m)).clamp(-1.0, 1.0)
+        } else {
+            0.0
+        };
+        2.0 * m * (cos.acos() / 3.0).cos()
+    };
+
+    polish(y - a / 3.0, |x| {
+        (((x + a) * x + b) * x + c, (3.0 * x + 2.0 * a) * x + b)
+    })
+}
+
+// real roots of x^4 + a x^3 + b x^2 + c x + d in increasing order, by ferrari's method. each
+// root gets a few newton steps on the original quartic to win back what the resolvent loses
+pub fn solve_quartic(a: f64, b: f64, c: f64, d: f64) -> Vec<f64> {
+    // x = y - a / 4 gives y^4 + p y^2 + q y + r
+    let a2 = a * a;
+    let p = b - 3.0 * a2 / 8.0;
+    let q = c - a * b / 2.0 + a2 * a / 8.0;
+    let r = d - a * c / 4.0 + a2 * b / 16.0 - 3.0 * a2 * a2 / 256.0;
+
+    let mut ys = vec![];
+    let mut push_quadratic = |b: f64, c: f64| {
+        if let Some((y0, y1)) = solve_quadratic(1.0, b, c) {
+            ys.push(y0);
+            ys.push(y1);
+        }
+    };
+
+    if q.abs() < 1e-12 {
+        // biquadratic, z = y^2
+        if let Some((z0, z1)) = solve_quadratic(1.0, p, r) {
+            for z in [z0, z1] {
+                if z >= 0.0 {
+                    push_quadratic(0.0, -z);
+                }
+            }
+        }
+    } else {
+        // (y^2 + p/2 + m)^2 = (sqrt(2m) y - q / (2 sqrt(2m)))^2 for m a root of the resolvent
+        let m = largest_cubic_root(p, p * p / 4.0 - r, -q * q / 8.0);
+        if m <= 0.0 {
+            return vec![];
+        }
+        let s = (2.0 * m).sqrt();
+        push_quadratic(-s, p / 2.0 + m + q / (2.0 * s));
+        push_quadratic(s, p / 2.0 + m - q / (2.0 * s));
+    }
+
+    let mut roots: Vec<f64> = ys
+        .into_iter()
+        .map(|y| {
+            polish(y - a / 4.0, |x| {
+                (
+                    (((x + a) * x + b) * x + c) * x + d,
+                    ((4.0 * x + 3.0 * a) * x + 2.0 * b) * x + c,
+                )
+            })
+        })
+        .collect();
+    roots.sort_by(|x, y| x.total_cmp(y));
+    roots
+}
+
+// newton's method from x, where f gives the value and the derivative
+fn polish(mut x: f64, f: impl Fn(f64) -> (f64, f64)) -> f64 {
+    for _ in 0..3 {
+        let (value, slope) = f(x);
+        if slope.abs() < 1e-12 {
+            break;
+        }
+        x -= value / slope;
+    }
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{solve_quartic, Torus};
+    use crate::{
+        hittable::Hittable,
+        material::{Lambertain, Material},
+        ray::Ray,
+        vec3::{Point, Vec3},
+    };
+
+    fn assert_roots(roots: Vec<f64>, expected: &[f64]) {
+        assert_eq!(roots.len(), expected.len(), "roots {:?}", roots);
+        for (r, e) in roots.iter().zip(expected) {
+            assert!((r - e).abs() < 1e-6, "roots {:?}", roots);
+        }
+    }
+
+    #[test]
+    fn four_distinct_roots() {
+        // (x - 1)(x - 2)(x - 3)(x - 4)
+        assert_roots(
+            solve_quartic(-10.0, 35.0, -50.0, 24.0),
+            &[1.0, 2.0, 3.0, 4.0],
+        );
+    }
+
+    #[test]
+    fn biquadratic() {
+        // (x^2 - 1)(x^2 - 9)
+        assert_roots(solve_quartic(0.0, -10.0, 0.0, 9.0), &[-3.0, -1.0, 1.0, 3.0]);
+    }
+
+    #[test]
+    fn two_real_roots() {
+        // (x + 2)(x - 5)(x^2 + 1)
+        assert_roots(solve_quartic(-3.0, -9.0, -3.0, -10.0), &[-2.0, 5.0]);
+    }
+
+    #[test]
+    fn no_real_roots() {
+        // (x^2 + 1)(x^2 + 4)
+        assert_roots(solve_quartic(0.0, 5.0, 0.0, 4.0), &[]);
+    }
+
+    #[test]
+    fn torus_from_far_away() {
+        let torus = Torus::new(
+            Point::new(0.0, 0.0, 0.0),
+            2.0,
+            0.5,
+            Arc::new(Material::Lambertain(Lambertain::default())),
+        );
+
+        // straight down through the tube, starting a long way off
+        let r = Ray::new(Point::new(2.0, 1e4, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let rec = torus.hit(&r, 0.001, f64::MAX).unwrap();
+        assert!((rec.p.y - 0.5).abs() < 1e-6);
+
+        // through the hole
+        let r = Ray::new(Point::new(0.0, 1e4, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        assert!(torus.hit(&r, 0.001, f64::MAX).is_none());
+
+        // sideways across the ring, entering its outside edge first
+        let r = Ray::new(Point::new(-1e4, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let rec = torus.hit(&r, 0.001, f64::MAX).unwrap();
+        assert!((rec.p.x + 2.5).abs() < 1e-6);
+    }
+}
@@ -39,6 +39,29 @@ pub fn clamp(x: f64, min: f64, max: f64) -> f64 {
     }
 }
 
+// real roots of a t^2 + b t + c, smallest first. falls back to the linear root when a is 0
+pub fn solve_quadratic(a: f64, b: f64, c: f64) -> Option<(f64, f64)> {
+    if a.abs() < 1e-12 {
+        if b.abs() < 1e-12 {
+            return None;
+        }
+        return Some((-c / b, -c / b));
+    }
+
+    let disc = b * b - 4.0 * a * c;
+    if disc < 0.0 {
+        return None;
+    }
+    // avoids the cancellation in -b + sqrt(disc) when b is large
+    let q = -0.5 * (b + b.signum() * disc.sqrt());
+    if q == 0.0 {
+        return Some((0.0, 0.0));
+    }
+
+    let (t0, t1) = (q / a, c / q);
+    Some(if t0 < t1 { (t0, t1) } else { (t1, t0) })
+}
+
 pub fn get_all_lights(v: &Vec<Arc<Object>>) -> Vec<Arc<Object>> {
     v.iter()
         .flat_map(|o| {